@@ -0,0 +1,134 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use tg_public_log_parser::parsers::{
+    game::{
+        parse_line, parse_line_with_policy, process_game_log, process_game_log_with_policy,
+        GameLogPolicy,
+    },
+    ip_filtering::filter_ips,
+};
+
+// A few MB of the kinds of lines a real game.log has the most of
+fn game_log() -> String {
+    let lines = [
+        "[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"hello there\" (Bridge (123,45,2))",
+        "[2024-01-02 03:04:05.678] ACCESS: Login: Somebody/(Some Body) from 1.2.3.4-1234567890 || BYOND v515.1630",
+        "[2024-01-02 03:04:05.678] ADMINPRIVATE: Something private happened",
+        "[2024-01-02 03:04:05.678] GAME-ATTACK: Somebody/(Some Body) has punched Someone/(Some One)",
+    ];
+
+    let mut contents = String::new();
+    while contents.len() < 4 * 1024 * 1024 {
+        for line in lines {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+    }
+
+    contents
+}
+
+fn bench_process_game_log(c: &mut Criterion) {
+    let contents = game_log();
+
+    let mut group = c.benchmark_group("process_game_log");
+    group.throughput(Throughput::Bytes(contents.len() as u64));
+    group.sample_size(20);
+
+    group.bench_function("reserved", |b| {
+        b.iter_batched(|| contents.clone(), process_game_log, BatchSize::LargeInput)
+    });
+
+    // How process_game_log used to build its output, for comparison
+    group.bench_function("fold", |b| {
+        b.iter(|| {
+            filter_ips(&contents)
+                .lines()
+                .map(parse_line)
+                .fold(String::new(), |a, b| a + &b + "\n")
+        })
+    });
+
+    group.finish();
+}
+
+// ADMIN lines go through a RegexSet unless ADMIN is a safe log type
+fn bench_safe_log_types(c: &mut Criterion) {
+    let lines = [
+        "[2024-01-02 03:04:05.678] ADMIN: Somebody/(Some Body) jumped to Bridge (123,45,2)",
+        "[2024-01-02 03:04:05.678] ADMIN: Somebody/(Some Body) has toggled their admin ghost",
+        "[2024-01-02 03:04:05.678] ADMIN: Somebody/(Some Body) : teleported Someone",
+    ];
+
+    let safe_policy = GameLogPolicy {
+        safe_log_types: vec!["ADMIN".to_owned()],
+        ..Default::default()
+    };
+
+    let mut group = c.benchmark_group("safe_log_types");
+    group.throughput(Throughput::Elements(lines.len() as u64));
+
+    for (name, policy) in [("default", GameLogPolicy::default()), ("safe", safe_policy)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for line in lines {
+                    std::hint::black_box(parse_line_with_policy(line, &policy));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// Mostly ATTACK lines with the odd login, which only skip the IP regex when ATTACK is an IP free log type
+fn bench_ip_free_log_types(c: &mut Criterion) {
+    let attack_lines = [
+        "[2024-01-02 03:04:05.678] GAME-ATTACK: Somebody/(Some Body) has punched Someone/(Some One) (Bridge (123,45,2))",
+        "[2024-01-02 03:04:05.678] GAME-ATTACK: Someone/(Some One) has shot Somebody/(Some Body) with the laser gun (Bridge (123,45,2))",
+        "[2024-01-02 03:04:05.678] GAME-ATTACK: Somebody/(Some Body) has attacked the airlock with the toolbox (NEWHP: 200) (Bridge (123,45,2))",
+    ];
+    let login_line = "[2024-01-02 03:04:05.678] ACCESS: Login: Somebody/(Some Body) from 1.2.3.4-1234567890 || BYOND v515.1630";
+
+    let mut contents = String::new();
+    let mut index = 0;
+    while contents.len() < 4 * 1024 * 1024 {
+        contents.push_str(match index % 50 {
+            0 => login_line,
+            index => attack_lines[index % attack_lines.len()],
+        });
+        contents.push('\n');
+        index += 1;
+    }
+
+    let ip_free_policy = GameLogPolicy {
+        ip_free_log_types: vec!["ATTACK".to_owned()],
+        ..Default::default()
+    };
+
+    let mut group = c.benchmark_group("ip_free_log_types");
+    group.throughput(Throughput::Bytes(contents.len() as u64));
+    group.sample_size(20);
+
+    for (name, policy) in [
+        ("default", GameLogPolicy::default()),
+        ("ip_free", ip_free_policy),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || contents.clone(),
+                |contents| process_game_log_with_policy(contents, &policy),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_process_game_log,
+    bench_safe_log_types,
+    bench_ip_free_log_types
+);
+criterion_main!(benches);
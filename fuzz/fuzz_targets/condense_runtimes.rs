@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tg_public_log_parser::parsers::runtimes::{
+    condense_runtimes_to_json, condense_runtimes_to_string, process_runtimes_log, runtime_counts,
+};
+
+fuzz_target!(|contents: &str| {
+    let _ = condense_runtimes_to_string(contents);
+    let _ = condense_runtimes_to_json(contents);
+    let _ = runtime_counts(contents);
+    let _ = process_runtimes_log(contents.to_owned());
+});
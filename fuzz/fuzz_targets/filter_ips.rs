@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tg_public_log_parser::parsers::ip_filtering::{filter_ips, find_ips};
+
+fuzz_target!(|contents: &str| {
+    let filtered = filter_ips(contents);
+
+    // Anything left over would be published
+    assert_eq!(find_ips(&filtered).count(), 0, "{contents:?}");
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tg_public_log_parser::parsers::game::{
+    parse_line, parse_line_with_policy, process_game_log, GameLogPolicy,
+};
+
+fuzz_target!(|contents: &str| {
+    let _ = parse_line(contents);
+    let _ = parse_line_with_policy(contents, &GameLogPolicy::strict());
+    let _ = process_game_log(contents.to_owned());
+});
@@ -1,9 +1,13 @@
 use std::{
+    collections::HashMap,
     net::SocketAddr,
     path::{Path, PathBuf},
 };
 
-use crate::ongoing_round_protection::{OngoingRoundProtection, OngoingRoundProtectionConfig};
+use eyre::Context;
+use tg_public_log_parser::ongoing_round_protection::{
+    OngoingRoundProtection, OngoingRoundProtectionConfig,
+};
 
 #[derive(Debug)]
 pub struct AppState {
@@ -19,12 +23,20 @@ impl AppState {
         Ok(AppState {
             ongoing_round_protection: OngoingRoundProtection::new(
                 config.ongoing_round_protection.take().unwrap(),
-            ),
+            )?,
 
             config,
         })
     }
 
+    // Does a single serverinfo fetch so --check-config can tell if it's reachable
+    pub async fn check_config(&self) -> eyre::Result<HashMap<String, u64>> {
+        self.ongoing_round_protection
+            .fetch_round_ids()
+            .await
+            .context("fetching serverinfo")
+    }
+
     pub async fn path_is_ongoing_round(&self, path: &Path) -> eyre::Result<bool> {
         self.ongoing_round_protection
             .path_is_ongoing_round(path)
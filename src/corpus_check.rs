@@ -0,0 +1,247 @@
+// A last check over sanitized output, such as a public mirror, for anything identifying that should never
+// have made it through. Doesn't sanitize anything itself, only reports where it found something, so a
+// pipeline regression fails CI instead of being published.
+use std::{
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use eyre::Context;
+use regex::Regex;
+
+use crate::parsers::ip_filtering::ip_regex;
+
+static MAC_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:[0-9A-Fa-f]{2}[:-]){5}[0-9A-Fa-f]{2}\b").unwrap());
+
+#[derive(Clone, Debug)]
+pub struct IdentifierPattern {
+    // What's reported for a match, like "ip"
+    pub name: String,
+    pub regex: Regex,
+}
+
+impl IdentifierPattern {
+    pub fn new(name: impl Into<String>, regex: Regex) -> Self {
+        Self {
+            name: name.into(),
+            regex,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CorpusCheck {
+    pub patterns: Vec<IdentifierPattern>,
+}
+
+// IPs and MAC addresses
+impl Default for CorpusCheck {
+    fn default() -> Self {
+        Self {
+            patterns: vec![
+                IdentifierPattern::new("ip", ip_regex().clone()),
+                IdentifierPattern::new("mac", MAC_REGEX.clone()),
+            ],
+        }
+    }
+}
+
+impl CorpusCheck {
+    // For anything else that should never be published, like a server's own hostnames
+    pub fn with_pattern(mut self, name: impl Into<String>, regex: Regex) -> Self {
+        self.patterns.push(IdentifierPattern::new(name, regex));
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorpusFinding {
+    pub path: PathBuf,
+    // Starting from 1
+    pub line_number: usize,
+    pub pattern: String,
+    pub matched: String,
+}
+
+impl fmt::Display for CorpusFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} {:?}",
+            self.path.display(),
+            self.line_number,
+            self.pattern,
+            self.matched
+        )
+    }
+}
+
+// Every match of every pattern under root, sorted by path and line. Gzipped files, like from gzip_output,
+// are checked decompressed. Files that aren't UTF-8 are checked as lossily as they'd be shown.
+pub fn check_corpus(root: &Path, check: &CorpusCheck) -> eyre::Result<Vec<CorpusFinding>> {
+    let mut findings = Vec::new();
+    check_directory(root, check, &mut findings)?;
+
+    findings.sort_by(|a, b| (&a.path, a.line_number).cmp(&(&b.path, b.line_number)));
+    Ok(findings)
+}
+
+// For tests and CI, panics listing every finding if there are any
+pub fn assert_corpus_clean(root: &Path, check: &CorpusCheck) {
+    let findings = check_corpus(root, check)
+        .unwrap_or_else(|error| panic!("couldn't check {}: {error:?}", root.display()));
+
+    if !findings.is_empty() {
+        let report = findings
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        panic!(
+            "{} has {} identifier(s) that should have been censored:\n{report}",
+            root.display(),
+            findings.len()
+        );
+    }
+}
+
+fn check_directory(
+    directory: &Path,
+    check: &CorpusCheck,
+    findings: &mut Vec<CorpusFinding>,
+) -> eyre::Result<()> {
+    let read_dir = std::fs::read_dir(directory)
+        .with_context(|| format!("reading directory {}", directory.display()))?;
+
+    for entry in read_dir {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            check_directory(&path, check, findings)?;
+        } else {
+            check_file(&path, check, findings)
+                .with_context(|| format!("checking {}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_file(
+    path: &Path,
+    check: &CorpusCheck,
+    findings: &mut Vec<CorpusFinding>,
+) -> eyre::Result<()> {
+    let mut contents = std::fs::read(path)?;
+
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(contents.as_slice()).read_to_end(&mut decompressed)?;
+        contents = decompressed;
+    }
+
+    let contents = String::from_utf8_lossy(&contents);
+
+    for (index, line) in contents.lines().enumerate() {
+        for pattern in &check.patterns {
+            for found in pattern.regex.find_iter(line) {
+                findings.push(CorpusFinding {
+                    path: path.to_owned(),
+                    line_number: index + 1,
+                    pattern: pattern.name.clone(),
+                    matched: found.as_str().to_owned(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn test_directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "tg-public-log-parser-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn test_check_corpus() {
+        let root = test_directory("corpus-check");
+        let round = root.join("2024/01/02/round-1");
+        std::fs::create_dir_all(&round).unwrap();
+
+        std::fs::write(
+            round.join("game.log"),
+            "clean\nfrom -censored-\nleaked 1.2.3.4\n",
+        )
+        .unwrap();
+        std::fs::write(round.join("attack.log"), "nic 00:1A:2b:3C:4d:5E\n").unwrap();
+        std::fs::write(round.join("clean.log"), "nothing here, not even 1.2.3\n").unwrap();
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"fine\nalso 5.6.7.8\n").unwrap();
+        std::fs::write(round.join("runtime.log.gz"), encoder.finish().unwrap()).unwrap();
+
+        let findings = check_corpus(&root, &CorpusCheck::default()).unwrap();
+        assert_eq!(
+            findings,
+            vec![
+                CorpusFinding {
+                    path: round.join("attack.log"),
+                    line_number: 1,
+                    pattern: "mac".to_owned(),
+                    matched: "00:1A:2b:3C:4d:5E".to_owned(),
+                },
+                CorpusFinding {
+                    path: round.join("game.log"),
+                    line_number: 3,
+                    pattern: "ip".to_owned(),
+                    matched: "1.2.3.4".to_owned(),
+                },
+                CorpusFinding {
+                    path: round.join("runtime.log.gz"),
+                    line_number: 2,
+                    pattern: "ip".to_owned(),
+                    matched: "5.6.7.8".to_owned(),
+                },
+            ]
+        );
+
+        let check =
+            CorpusCheck::default().with_pattern("hostname", Regex::new(r"\bnothing\b").unwrap());
+        assert_eq!(check_corpus(&root, &check).unwrap().len(), 4);
+
+        let report =
+            std::panic::catch_unwind(|| assert_corpus_clean(&root, &CorpusCheck::default()))
+                .unwrap_err();
+        let report = report.downcast_ref::<String>().unwrap();
+        assert!(report.contains("has 3 identifier(s)"));
+        assert!(report.contains(&format!(
+            "{}:3: ip \"1.2.3.4\"",
+            round.join("game.log").display()
+        )));
+
+        std::fs::remove_file(round.join("game.log")).unwrap();
+        std::fs::remove_file(round.join("attack.log")).unwrap();
+        std::fs::remove_file(round.join("runtime.log.gz")).unwrap();
+        assert_corpus_clean(&root, &CorpusCheck::default());
+
+        assert!(check_corpus(&root.join("missing"), &CorpusCheck::default()).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
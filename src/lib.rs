@@ -0,0 +1,11 @@
+#[cfg(feature = "server")]
+pub mod corpus_check;
+#[cfg(feature = "server")]
+pub mod ongoing_round_protection;
+pub mod parsers;
+#[cfg(feature = "server")]
+pub mod sanitize_tree;
+#[cfg(feature = "server")]
+pub mod sanitizer;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -2,15 +2,22 @@ use std::sync::Arc;
 
 use axum::Router;
 use eyre::Context;
+use tg_public_log_parser::{corpus_check, parsers::runtimes};
 use tracing_subscriber::prelude::*;
 
 mod app_state;
-mod ongoing_round_protection;
-mod parsers;
 mod route;
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("condense") {
+        return condense(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("check-corpus") {
+        return check_corpus(&args[1..]);
+    }
+
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
         .with(tracing_subscriber::EnvFilter::from_default_env())
@@ -22,6 +29,13 @@ async fn main() -> eyre::Result<()> {
             .await
             .context("loading app state")?,
     );
+
+    if std::env::args().any(|arg| arg == "--check-config") {
+        let round_ids = state.check_config().await.context("checking config")?;
+        println!("config is valid, current round ids: {round_ids:?}");
+        return Ok(());
+    }
+
     tracing::info!("hosting on {}", state.config.address);
 
     let listener = tokio::net::TcpListener::bind(state.config.address).await?;
@@ -35,3 +49,67 @@ async fn main() -> eyre::Result<()> {
 
     Ok(())
 }
+
+// `condense <path> [--json] [--compact]` prints one runtime.log condensed, without needing config.toml.
+// Anything logged goes to stderr, so stdout is only the condensed output.
+fn condense(args: &[String]) -> eyre::Result<()> {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let json = args.iter().any(|arg| arg == "--json");
+    let compact = args.iter().any(|arg| arg == "--compact");
+    let [path] = args
+        .iter()
+        .filter(|arg| *arg != "--json" && *arg != "--compact")
+        .collect::<Vec<_>>()[..]
+    else {
+        eyre::bail!("usage: condense <path> [--json] [--compact]");
+    };
+
+    let contents = std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+
+    if json {
+        println!("{}", runtimes::condense_runtimes_to_json(&contents));
+    } else {
+        let options = runtimes::CondenseOptions {
+            spacing: if compact {
+                runtimes::CondensedSpacing::Compact
+            } else {
+                runtimes::CondensedSpacing::Legacy
+            },
+            ..Default::default()
+        };
+
+        print!(
+            "{}",
+            runtimes::condense_runtimes_to_string_with_options(&contents, &options)
+        );
+    }
+
+    Ok(())
+}
+
+// `check-corpus <path>` lists every IP and MAC address left in already sanitized output, such as a
+// public mirror, and fails if there are any. For CI, so it doesn't need config.toml either.
+fn check_corpus(args: &[String]) -> eyre::Result<()> {
+    let [path] = args else {
+        eyre::bail!("usage: check-corpus <path>");
+    };
+
+    let findings = corpus_check::check_corpus(
+        std::path::Path::new(path),
+        &corpus_check::CorpusCheck::default(),
+    )?;
+
+    for finding in &findings {
+        println!("{finding}");
+    }
+
+    if !findings.is_empty() {
+        eyre::bail!("{path} has {} identifier(s) in it", findings.len());
+    }
+
+    Ok(())
+}
@@ -1,78 +1,255 @@
 use std::{
-    collections::HashMap,
-    path::Path,
-    sync::{Arc, OnceLock},
-    time::Duration,
+    collections::{hash_map::Entry, HashMap},
+    future::Future,
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, LazyLock, OnceLock},
+    time::{Duration, Instant},
 };
 
 use eyre::Context;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use tokio::task::JoinHandle;
 
 type OngoingRoundIds = Arc<parking_lot::Mutex<HashMap<String, u64>>>;
 
+// Lets every OngoingRoundProtection in a process share connections and settings like timeouts or proxies,
+// and optionally caps how many serverinfo requests they make at once. Cloning it shares both.
+#[derive(Clone, Debug)]
+pub struct ServerInfoClient {
+    client: reqwest::Client,
+    request_permits: Option<Arc<tokio::sync::Semaphore>>,
+}
+
+impl ServerInfoClient {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            request_permits: None,
+        }
+    }
+
+    pub fn with_concurrency_limit(mut self, max_concurrent_requests: usize) -> Self {
+        self.request_permits = Some(Arc::new(tokio::sync::Semaphore::new(
+            max_concurrent_requests,
+        )));
+        self
+    }
+
+    async fn fetch(&self, url: &str, headers: &HeaderMap) -> eyre::Result<Vec<u8>> {
+        let _permit = match &self.request_permits {
+            Some(request_permits) => Some(request_permits.acquire().await?),
+            None => None,
+        };
+
+        let bytes = self
+            .client
+            .get(url)
+            .headers(headers.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        Ok(bytes.to_vec())
+    }
+}
+
+pub type RoundIdsFuture<'a> =
+    Pin<Box<dyn Future<Output = eyre::Result<HashMap<String, u64>>> + Send + 'a>>;
+
+// Where the current round id of each server comes from, for forks with a status endpoint
+// that isn't shaped like tgstation's serverinfo.json. Boxed rather than an async fn so it can be a dyn.
+pub trait RoundSource: std::fmt::Debug + Send + Sync {
+    // Server identifier to the round id it's on right now
+    fn current_round_ids(&self) -> RoundIdsFuture<'_>;
+}
+
+// tgstation's serverinfo.json, which lists every server with its identifier and round id
+#[derive(Debug)]
+pub struct ServerInfoRoundSource {
+    client: ServerInfoClient,
+    url: String,
+    headers: HeaderMap,
+}
+
+impl ServerInfoRoundSource {
+    pub fn new(client: ServerInfoClient, url: impl Into<String>, headers: HeaderMap) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            headers,
+        }
+    }
+}
+
+impl RoundSource for ServerInfoRoundSource {
+    fn current_round_ids(&self) -> RoundIdsFuture<'_> {
+        Box::pin(async move {
+            let server_info_bytes = self.client.fetch(&self.url, &self.headers).await?;
+            round_ids_from_server_info(&server_info_bytes)
+        })
+    }
+}
+
+// A flat {"identifier": round_id} object, with the round ids as numbers or strings, and null for no round
+#[derive(Debug)]
+pub struct FlatMapRoundSource {
+    client: ServerInfoClient,
+    url: String,
+    headers: HeaderMap,
+}
+
+impl FlatMapRoundSource {
+    pub fn new(client: ServerInfoClient, url: impl Into<String>, headers: HeaderMap) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            headers,
+        }
+    }
+}
+
+impl RoundSource for FlatMapRoundSource {
+    fn current_round_ids(&self) -> RoundIdsFuture<'_> {
+        Box::pin(async move {
+            let bytes = self.client.fetch(&self.url, &self.headers).await?;
+            round_ids_from_flat_map(&bytes)
+        })
+    }
+}
+
+// Which built in RoundSource to read serverinfo with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerInfoFormat {
+    // See ServerInfoRoundSource
+    #[default]
+    Tgstation,
+
+    // See FlatMapRoundSource
+    FlatMap,
+}
+
 #[derive(Debug)]
 pub struct OngoingRoundProtection {
     config: OngoingRoundProtectionConfig,
+    round_source: Arc<dyn RoundSource>,
 
     last_known_round_ids: tokio::sync::OnceCell<OngoingRoundIds>,
+    // When round ids were last fetched, whether or not they were kept
+    last_success: Arc<parking_lot::Mutex<Option<Instant>>>,
     round_id_loop: OnceLock<JoinHandle<()>>,
 }
 
+// For readiness probes, so nothing is sent to an instance that can't tell what's ongoing yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProtectionHealth {
+    // Whether round ids have been fetched at least once
+    pub initialized: bool,
+    pub last_success: Option<Instant>,
+    // How many servers have a round id
+    pub known_count: usize,
+}
+
 impl OngoingRoundProtection {
-    pub fn new(config: OngoingRoundProtectionConfig) -> Self {
-        Self {
+    pub fn new(config: OngoingRoundProtectionConfig) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder()
+            .build()
+            .context("creating serverinfo client")?;
+
+        Self::with_client(config, ServerInfoClient::new(client))
+    }
+
+    // For running several of these in one process, see ServerInfoClient.
+    // The config's headers are still sent with every request.
+    pub fn with_client(
+        config: OngoingRoundProtectionConfig,
+        client: ServerInfoClient,
+    ) -> eyre::Result<Self> {
+        config.validate()?;
+
+        let headers = config.headers.header_map()?;
+        let url = config.serverinfo.clone();
+        let round_source: Arc<dyn RoundSource> = match config.serverinfo_format {
+            ServerInfoFormat::Tgstation => {
+                Arc::new(ServerInfoRoundSource::new(client, url, headers))
+            }
+            ServerInfoFormat::FlatMap => Arc::new(FlatMapRoundSource::new(client, url, headers)),
+        };
+
+        Self::with_round_source(config, round_source)
+    }
+
+    // For getting round ids from somewhere else entirely. The config's serverinfo, headers,
+    // and serverinfo_format are left unused.
+    pub fn with_round_source(
+        config: OngoingRoundProtectionConfig,
+        round_source: Arc<dyn RoundSource>,
+    ) -> eyre::Result<Self> {
+        config.validate()?;
+
+        Ok(Self {
             config,
+            round_source,
             last_known_round_ids: Default::default(),
+            last_success: Default::default(),
             round_id_loop: OnceLock::new(),
+        })
+    }
+
+    // Only looks at what's already been fetched, so it never fetches anything itself
+    pub async fn health(&self) -> ProtectionHealth {
+        let known_count = self
+            .last_known_round_ids
+            .get()
+            .map(|last_known_round_ids| last_known_round_ids.lock().len());
+
+        ProtectionHealth {
+            initialized: known_count.is_some(),
+            last_success: *self.last_success.lock(),
+            known_count: known_count.unwrap_or(0),
         }
     }
 
-    pub async fn path_is_ongoing_round(&self, path: &Path) -> eyre::Result<bool> {
+    // Fetches serverinfo once, without touching the known round ids
+    pub async fn fetch_round_ids(&self) -> eyre::Result<HashMap<String, u64>> {
+        self.round_source.current_round_ids().await
+    }
+
+    // The current round ids, for checking a lot of paths at once without touching the lock each time
+    pub async fn snapshot(&self) -> eyre::Result<OngoingRoundsSnapshot> {
         let last_known_round_ids = self.last_known_round_ids().await?;
-        let last_known_round_ids = last_known_round_ids.lock();
+        let round_ids = last_known_round_ids.lock().clone();
 
-        for ancestor in path.ancestors() {
-            let filename = match ancestor.file_name() {
-                Some(filename) => filename,
-                None => break,
-            }
-            .to_string_lossy();
+        Ok(OngoingRoundsSnapshot {
+            config: self.config.clone(),
+            round_ids,
+        })
+    }
 
-            if let Some(round_id_text) = filename.strip_prefix("round-") {
-                let round_id: u64 = round_id_text.parse().context("parsing round id")?;
+    pub async fn path_is_ongoing_round(&self, path: &Path) -> eyre::Result<bool> {
+        let last_known_round_ids = self.last_known_round_ids().await?;
+        let last_known_round_ids = last_known_round_ids.lock();
 
-                let server_identifier = match &self.config.paths_to_identifiers {
-                    Some(paths_to_identifiers) => Some(paths_to_identifiers.get(&*filename)),
-                    None => None,
-                };
+        path_is_ongoing_round(&self.config, &last_known_round_ids, path)
+    }
 
-                match server_identifier {
-                    Some(Some(server_identifier)) => {
-                        if let Some(&ongoing_round_id) = last_known_round_ids.get(server_identifier)
-                        {
-                            return Ok(round_id >= ongoing_round_id);
-                        }
-                    }
-                    None => {
-                        for (_, ongoing_round_id) in last_known_round_ids.iter() {
-                            if *ongoing_round_id == round_id {
-                                return Ok(true);
-                            }
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
+    // For logging and debugging why a path was or wasn't published
+    pub async fn round_info_for_path(&self, path: &Path) -> eyre::Result<Option<RoundInfo>> {
+        let last_known_round_ids = self.last_known_round_ids().await?;
+        let last_known_round_ids = last_known_round_ids.lock();
 
-        Ok(false)
+        round_info_for_path(&self.config, &last_known_round_ids, path)
     }
 
     async fn last_known_round_ids(&self) -> eyre::Result<OngoingRoundIds> {
         let last_known_round_ids = self
             .last_known_round_ids
             .get_or_try_init(|| async {
-                let round_ids = fetch_ongoing_rounds(&self.config.serverinfo).await?;
+                let round_ids = self.fetch_round_ids().await?;
+                *self.last_success.lock() = Some(Instant::now());
                 Ok(Arc::new(parking_lot::Mutex::new(round_ids))) as eyre::Result<OngoingRoundIds>
             })
             .await?
@@ -80,22 +257,44 @@ impl OngoingRoundProtection {
 
         self.round_id_loop.get_or_init({
             let last_known_round_ids: OngoingRoundIds = Arc::clone(&last_known_round_ids);
-            let serverinfo = self.config.serverinfo.clone();
+            let round_source = Arc::clone(&self.round_source);
+            let last_success = Arc::clone(&self.last_success);
+            let empty_round_ids = self.config.empty_round_ids;
+            let refresh_interval = Duration::from_secs(self.config.refresh_interval_secs);
 
             move || {
                 tokio::task::spawn(async move {
+                    let mut failures = RepeatedFailures::default();
+
                     loop {
-                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        tokio::time::sleep(refresh_interval).await;
                         tracing::debug!("getting new round ids...");
-                        let round_ids = match fetch_ongoing_rounds(&serverinfo).await {
+                        let round_ids = match round_source.current_round_ids().await {
                             Ok(round_ids) => round_ids,
                             Err(error) => {
-                                tracing::error!("error getting ongoing rounds: {error}");
+                                if let Some(message) =
+                                    failures.record_failure(&error.to_string(), Instant::now())
+                                {
+                                    tracing::error!("error getting ongoing rounds: {message}");
+                                }
+
                                 continue;
                             }
                         };
 
-                        *last_known_round_ids.lock() = round_ids;
+                        *last_success.lock() = Some(Instant::now());
+
+                        if let Some(failure_count) = failures.record_success() {
+                            tracing::info!(
+                                "got ongoing rounds again after {failure_count} failure(s)"
+                            );
+                        }
+
+                        update_round_ids(
+                            empty_round_ids,
+                            &mut last_known_round_ids.lock(),
+                            round_ids,
+                        );
                     }
                 })
             }
@@ -105,6 +304,65 @@ impl OngoingRoundProtection {
     }
 }
 
+// So that serverinfo being down doesn't log an error every refresh. A new error is always logged,
+// but the same one again is only logged every REPEAT_LOG_EVERY times or REPEAT_LOG_INTERVAL, with how many there were.
+#[derive(Debug, Default)]
+struct RepeatedFailures {
+    last_error: Option<String>,
+    // Of last_error, in a row
+    repeats: u64,
+    // Since the last one that was logged
+    unlogged: u64,
+    last_logged: Option<Instant>,
+    // Of any error, since the last success
+    total: u64,
+}
+
+impl RepeatedFailures {
+    const REPEAT_LOG_EVERY: u64 = 10;
+    const REPEAT_LOG_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+    // What to log for this failure, if anything
+    fn record_failure(&mut self, error: &str, now: Instant) -> Option<String> {
+        self.total += 1;
+
+        if self.last_error.as_deref() != Some(error) {
+            let message = match self.unlogged {
+                0 => error.to_owned(),
+                unlogged => format!("{error} (after {unlogged} more of the previous error)"),
+            };
+
+            self.last_error = Some(error.to_owned());
+            self.repeats = 1;
+            self.unlogged = 0;
+            self.last_logged = Some(now);
+            return Some(message);
+        }
+
+        self.repeats += 1;
+        self.unlogged += 1;
+
+        let interval_passed = self
+            .last_logged
+            .is_none_or(|last_logged| now.duration_since(last_logged) >= Self::REPEAT_LOG_INTERVAL);
+
+        if self.unlogged < Self::REPEAT_LOG_EVERY && !interval_passed {
+            return None;
+        }
+
+        self.unlogged = 0;
+        self.last_logged = Some(now);
+        Some(format!("{error} ({} times in a row)", self.repeats))
+    }
+
+    // How many failures in a row there were, if there were any
+    fn record_success(&mut self) -> Option<u64> {
+        let failure_count = self.total;
+        *self = Self::default();
+        (failure_count > 0).then_some(failure_count)
+    }
+}
+
 impl Drop for OngoingRoundProtection {
     fn drop(&mut self) {
         if let Some(round_id_loop) = self.round_id_loop.get() {
@@ -113,40 +371,436 @@ impl Drop for OngoingRoundProtection {
     }
 }
 
-async fn fetch_ongoing_rounds(serverinfo_url: &str) -> eyre::Result<HashMap<String, u64>> {
-    let server_info_bytes = reqwest::get(serverinfo_url)
-        .await?
-        .error_for_status()?
-        .bytes()
-        .await?;
+#[derive(Clone, Debug)]
+pub struct OngoingRoundsSnapshot {
+    config: OngoingRoundProtectionConfig,
+    round_ids: HashMap<String, u64>,
+}
+
+impl OngoingRoundsSnapshot {
+    // For when the round ids are already known, such as in a batch job
+    pub fn new(config: OngoingRoundProtectionConfig, round_ids: HashMap<String, u64>) -> Self {
+        Self { config, round_ids }
+    }
+
+    pub fn path_is_ongoing_round(&self, path: &Path) -> eyre::Result<bool> {
+        path_is_ongoing_round(&self.config, &self.round_ids, path)
+    }
+
+    pub fn round_info_for_path(&self, path: &Path) -> eyre::Result<Option<RoundInfo>> {
+        round_info_for_path(&self.config, &self.round_ids, path)
+    }
+
+    // See OngoingRoundProtectionConfig::round_id_of_folder
+    pub fn round_id_of_folder(&self, folder_name: &str) -> eyre::Result<Option<u64>> {
+        self.config.round_id_of_folder(folder_name)
+    }
+}
+
+// The round id of a folder named like round-<id>, None if it isn't a round folder.
+// For when there's no config with a round_folder_pattern to go by.
+pub fn round_id_of_folder(folder_name: &str) -> eyre::Result<Option<u64>> {
+    static DEFAULT_PATTERN: LazyLock<RoundFolderPattern> =
+        LazyLock::new(RoundFolderPattern::default);
+
+    DEFAULT_PATTERN.round_id(folder_name)
+}
+
+fn path_is_ongoing_round(
+    config: &OngoingRoundProtectionConfig,
+    last_known_round_ids: &HashMap<String, u64>,
+    path: &Path,
+) -> eyre::Result<bool> {
+    Ok(round_info_for_path(config, last_known_round_ids, path)?
+        .is_some_and(|round_info| round_info.ongoing))
+}
+
+// Which round a path is in, and why it was or wasn't considered ongoing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoundInfo {
+    pub round_id: u64,
+
+    // The server the round was matched to, None if it wasn't matched to any
+    pub identifier: Option<String>,
+
+    pub ongoing: bool,
+}
+
+fn round_info_for_path(
+    config: &OngoingRoundProtectionConfig,
+    last_known_round_ids: &HashMap<String, u64>,
+    path: &Path,
+) -> eyre::Result<Option<RoundInfo>> {
+    // The innermost round folder, if nothing decides whether any of them are ongoing
+    let mut innermost_round = None;
 
-    let server_info: ServerInfo = match serde_json::from_slice(&server_info_bytes) {
+    for ancestor in normalize_path(path).ancestors() {
+        let filename = match ancestor.file_name() {
+            Some(filename) => filename,
+            None => break,
+        }
+        .to_string_lossy();
+
+        if let Some(round_id) = config.round_folder_pattern.round_id(&filename)? {
+            if last_known_round_ids.is_empty()
+                && config.empty_round_ids == EmptyRoundIdsPolicy::AllOngoing
+            {
+                return Ok(Some(RoundInfo {
+                    round_id,
+                    identifier: None,
+                    ongoing: true,
+                }));
+            }
+
+            let server_identifier =
+                config
+                    .paths_to_identifiers
+                    .as_ref()
+                    .map(|paths_to_identifiers| {
+                        identifier_for_round_folder(paths_to_identifiers, ancestor, &filename)
+                    });
+
+            match server_identifier {
+                Some(Some(server_identifier)) => {
+                    if let Some(&ongoing_round_id) = last_known_round_ids.get(server_identifier) {
+                        return Ok(Some(RoundInfo {
+                            round_id,
+                            identifier: Some(server_identifier.clone()),
+                            ongoing: ongoing_round_id != NO_ACTIVE_ROUND
+                                && round_id >= ongoing_round_id,
+                        }));
+                    }
+                }
+                None => {
+                    for (identifier, ongoing_round_id) in last_known_round_ids.iter() {
+                        if *ongoing_round_id != NO_ACTIVE_ROUND && *ongoing_round_id == round_id {
+                            return Ok(Some(RoundInfo {
+                                round_id,
+                                identifier: Some(identifier.clone()),
+                                ongoing: true,
+                            }));
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            innermost_round.get_or_insert(RoundInfo {
+                round_id,
+                identifier: None,
+                ongoing: false,
+            });
+        }
+    }
+
+    Ok(innermost_round)
+}
+
+// The same round id can come up on more than one server, so a key can also be a folder the round is in
+// and the round folder, like "sybil/round-12345". Those are checked innermost folder first, then the
+// round folder on its own.
+fn identifier_for_round_folder<'a>(
+    paths_to_identifiers: &'a HashMap<String, String>,
+    round_folder: &Path,
+    round_folder_name: &str,
+) -> Option<&'a String> {
+    round_folder
+        .ancestors()
+        .skip(1)
+        .filter_map(|ancestor| ancestor.file_name())
+        .find_map(|ancestor_name| {
+            paths_to_identifiers.get(&format!(
+                "{}/{round_folder_name}",
+                ancestor_name.to_string_lossy()
+            ))
+        })
+        .or_else(|| paths_to_identifiers.get(round_folder_name))
+}
+
+// Resolves "." and ".." without touching the filesystem, so that however the path was put together,
+// every folder it's actually in shows up in its ancestors. Repeated and trailing separators
+// are already ignored by Path::components.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    normalized.pop();
+                } else {
+                    normalized.push(component);
+                }
+            }
+
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+fn round_ids_from_server_info(server_info_bytes: &[u8]) -> eyre::Result<HashMap<String, u64>> {
+    let server_info: ServerInfo = match serde_json::from_slice(server_info_bytes) {
         Ok(server_info) => server_info,
         Err(error) => {
             tracing::error!(
                 "bad serverinfo.json, contents = {}",
-                String::from_utf8_lossy(&server_info_bytes)
+                String::from_utf8_lossy(server_info_bytes)
             );
             return Err(error.into());
         }
     };
 
-    let round_ids = HashMap::from_iter(server_info.servers.into_iter().filter_map(|server| {
-        server.data.and_then(|data| match data.round_id {
-            Some(round_id) => Some((data.identifier, round_id.parse().expect("invalid round id"))),
-            None => None,
-        })
-    }));
+    let mut round_ids = HashMap::new();
+
+    for data in server_info
+        .servers
+        .into_iter()
+        .filter_map(|server| server.data)
+    {
+        let Some(round_id) = data.round_id else {
+            continue;
+        };
+        let round_id = parse_round_id(&round_id)
+            .with_context(|| format!("parsing round id of {}", data.identifier))?;
+
+        // Keep the highest, since a lower one would let through rounds that might still be going
+        match round_ids.entry(data.identifier) {
+            Entry::Occupied(mut entry) => {
+                tracing::warn!(
+                    "serverinfo lists {} more than once ({} and {round_id}), keeping the highest",
+                    entry.key(),
+                    entry.get()
+                );
+
+                if round_id > *entry.get() {
+                    entry.insert(round_id);
+                }
+            }
+
+            Entry::Vacant(entry) => {
+                entry.insert(round_id);
+            }
+        }
+    }
+
+    tracing::debug!("current round ids: {round_ids:?}");
+
+    Ok(round_ids)
+}
+
+// What servers report between rounds, as either 0 or a negative number. Kept in the round ids
+// so the server is still known, but never ongoing.
+const NO_ACTIVE_ROUND: u64 = 0;
+
+fn parse_round_id(round_id: &str) -> eyre::Result<u64> {
+    let round_id: i64 = round_id.trim().parse()?;
+    Ok(u64::try_from(round_id).unwrap_or(NO_ACTIVE_ROUND))
+}
+
+fn round_ids_from_flat_map(bytes: &[u8]) -> eyre::Result<HashMap<String, u64>> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum FlatRoundId {
+        Number(i64),
+        Text(String),
+    }
+
+    let flat_map: HashMap<String, Option<FlatRoundId>> =
+        serde_json::from_slice(bytes).context("parsing round ids")?;
+
+    let mut round_ids = HashMap::new();
+    for (identifier, round_id) in flat_map {
+        let round_id = match round_id {
+            Some(FlatRoundId::Number(round_id)) => {
+                u64::try_from(round_id).unwrap_or(NO_ACTIVE_ROUND)
+            }
+            Some(FlatRoundId::Text(round_id)) => parse_round_id(&round_id)
+                .with_context(|| format!("parsing round id of {identifier}"))?,
+            None => continue,
+        };
+
+        round_ids.insert(identifier, round_id);
+    }
 
     tracing::debug!("current round ids: {round_ids:?}");
 
     Ok(round_ids)
 }
 
-#[derive(Debug, serde::Deserialize)]
+fn update_round_ids(
+    policy: EmptyRoundIdsPolicy,
+    last_known_round_ids: &mut HashMap<String, u64>,
+    round_ids: HashMap<String, u64>,
+) {
+    if round_ids.is_empty()
+        && !last_known_round_ids.is_empty()
+        && policy == EmptyRoundIdsPolicy::KeepPrevious
+    {
+        tracing::warn!("serverinfo has no round ids, keeping the previous ones");
+        return;
+    }
+
+    *last_known_round_ids = round_ids;
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
 pub struct OngoingRoundProtectionConfig {
     serverinfo: String,
     paths_to_identifiers: Option<HashMap<String, String>>,
+
+    // Extra headers to send with the serverinfo request, such as for auth
+    #[serde(default)]
+    headers: SecretHeaders,
+
+    #[serde(default)]
+    empty_round_ids: EmptyRoundIdsPolicy,
+
+    #[serde(default = "default_refresh_interval_secs")]
+    refresh_interval_secs: u64,
+
+    #[serde(default)]
+    round_folder_pattern: RoundFolderPattern,
+
+    #[serde(default)]
+    serverinfo_format: ServerInfoFormat,
+}
+
+// Which folders are rounds, with the first capture group being the round id.
+// The default is "round-<id>", for archives that name them differently. Make it specific enough to not
+// match the date folders, since the innermost match is used when nothing decides whether a round is ongoing.
+#[derive(Clone, Debug)]
+struct RoundFolderPattern(regex::Regex);
+
+impl RoundFolderPattern {
+    fn round_id_text<'a>(&self, folder_name: &'a str) -> Option<&'a str> {
+        Some(self.0.captures(folder_name)?.get(1)?.as_str())
+    }
+
+    fn round_id(&self, folder_name: &str) -> eyre::Result<Option<u64>> {
+        self.round_id_text(folder_name)
+            .map(|round_id_text| round_id_text.parse().context("parsing round id"))
+            .transpose()
+    }
+}
+
+impl Default for RoundFolderPattern {
+    fn default() -> Self {
+        Self(regex::Regex::new(r"^round-(.*)$").unwrap())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for RoundFolderPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        let regex = regex::Regex::new(&pattern).map_err(serde::de::Error::custom)?;
+
+        if regex.captures_len() < 2 {
+            return Err(serde::de::Error::custom(
+                "round_folder_pattern needs a capture group for the round id",
+            ));
+        }
+
+        Ok(Self(regex))
+    }
+}
+
+fn default_refresh_interval_secs() -> u64 {
+    60
+}
+
+impl OngoingRoundProtectionConfig {
+    // The round id of a folder going by round_folder_pattern, None if it isn't a round folder
+    pub fn round_id_of_folder(&self, folder_name: &str) -> eyre::Result<Option<u64>> {
+        self.round_folder_pattern.round_id(folder_name)
+    }
+
+    // Catches misconfiguration that would otherwise only show up once we're serving requests
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.serverinfo.trim().is_empty() {
+            eyre::bail!("serverinfo is empty");
+        }
+
+        let serverinfo = reqwest::Url::parse(&self.serverinfo).context("parsing serverinfo url")?;
+        if !matches!(serverinfo.scheme(), "http" | "https") {
+            eyre::bail!(
+                "serverinfo must be an http(s) url, not {}",
+                serverinfo.scheme()
+            );
+        }
+
+        if self.refresh_interval_secs == 0 {
+            eyre::bail!("refresh_interval_secs must be above 0");
+        }
+
+        if let Some(paths_to_identifiers) = &self.paths_to_identifiers {
+            for (path, identifier) in paths_to_identifiers {
+                if path.is_empty() || identifier.is_empty() {
+                    eyre::bail!(
+                        "paths_to_identifiers has an empty entry ({path:?} = {identifier:?})"
+                    );
+                }
+            }
+        }
+
+        self.headers.header_map()?;
+
+        Ok(())
+    }
+}
+
+// What to do when serverinfo doesn't report any round ids, such as during a full restart
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyRoundIdsPolicy {
+    // Keep using the last round ids we got, if we ever got any
+    #[default]
+    KeepPrevious,
+
+    // Treat every round as finished
+    AllSafe,
+
+    // Treat every round as ongoing until serverinfo reports round ids again
+    AllOngoing,
+}
+
+// Header values are likely to be tokens, so keep them out of debug output
+#[derive(Clone, Default, serde::Deserialize)]
+#[serde(transparent)]
+struct SecretHeaders(HashMap<String, String>);
+
+impl std::fmt::Debug for SecretHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.0.keys().map(|name| (name, "<redacted>")))
+            .finish()
+    }
+}
+
+impl SecretHeaders {
+    fn header_map(&self) -> eyre::Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in &self.0 {
+            let mut value = HeaderValue::from_str(value)
+                .with_context(|| format!("invalid value for serverinfo header {name}"))?;
+            value.set_sensitive(true);
+
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("invalid serverinfo header name {name}"))?,
+                value,
+            );
+        }
+
+        Ok(headers)
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -164,3 +818,565 @@ struct ServerData {
     round_id: Option<String>,
     identifier: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_SERVER_INFO: &str = r#"{
+        "servers": [
+            { "data": null },
+            { "data": { "identifier": "sybil" } },
+            { "data": { "identifier": "manuel", "round_id": null } }
+        ]
+    }"#;
+
+    fn config(empty_round_ids: EmptyRoundIdsPolicy) -> OngoingRoundProtectionConfig {
+        OngoingRoundProtectionConfig {
+            serverinfo: "https://example.com/serverinfo.json".to_owned(),
+            paths_to_identifiers: None,
+            headers: SecretHeaders::default(),
+            empty_round_ids,
+            refresh_interval_secs: default_refresh_interval_secs(),
+            round_folder_pattern: RoundFolderPattern::default(),
+            serverinfo_format: ServerInfoFormat::default(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config(EmptyRoundIdsPolicy::default()).validate().is_ok());
+
+        for invalid in [
+            OngoingRoundProtectionConfig {
+                serverinfo: "".to_owned(),
+                ..config(EmptyRoundIdsPolicy::default())
+            },
+            OngoingRoundProtectionConfig {
+                serverinfo: "not a url".to_owned(),
+                ..config(EmptyRoundIdsPolicy::default())
+            },
+            OngoingRoundProtectionConfig {
+                serverinfo: "ftp://example.com/serverinfo.json".to_owned(),
+                ..config(EmptyRoundIdsPolicy::default())
+            },
+            OngoingRoundProtectionConfig {
+                refresh_interval_secs: 0,
+                ..config(EmptyRoundIdsPolicy::default())
+            },
+            OngoingRoundProtectionConfig {
+                paths_to_identifiers: Some(HashMap::from([("sybil".to_owned(), "".to_owned())])),
+                ..config(EmptyRoundIdsPolicy::default())
+            },
+            OngoingRoundProtectionConfig {
+                headers: SecretHeaders(HashMap::from([(
+                    "bad header".to_owned(),
+                    "value".to_owned(),
+                )])),
+                ..config(EmptyRoundIdsPolicy::default())
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{invalid:?} should be invalid");
+        }
+    }
+
+    #[test]
+    fn test_round_info_for_path() {
+        let round_ids = HashMap::from([("sybil".to_owned(), 100)]);
+        let config = config(EmptyRoundIdsPolicy::default());
+
+        assert_eq!(
+            round_info_for_path(
+                &config,
+                &round_ids,
+                Path::new("2024/01/01/round-100/game.log")
+            )
+            .unwrap(),
+            Some(RoundInfo {
+                round_id: 100,
+                identifier: Some("sybil".to_owned()),
+                ongoing: true,
+            })
+        );
+
+        assert_eq!(
+            round_info_for_path(
+                &config,
+                &round_ids,
+                Path::new("2024/01/01/round-99/game.log")
+            )
+            .unwrap(),
+            Some(RoundInfo {
+                round_id: 99,
+                identifier: None,
+                ongoing: false,
+            })
+        );
+
+        assert_eq!(
+            round_info_for_path(&config, &round_ids, Path::new("2024/01/01/game.log")).unwrap(),
+            None
+        );
+
+        assert!(round_info_for_path(&config, &round_ids, Path::new("round-abc/game.log")).is_err());
+    }
+
+    #[test]
+    fn test_round_folder_pattern() {
+        let round_ids = HashMap::from([("sybil".to_owned(), 100)]);
+        let numeric_config: OngoingRoundProtectionConfig = toml::from_str(
+            r#"
+                serverinfo = "https://example.com/serverinfo.json"
+                round_folder_pattern = '^([0-9]+)$'
+            "#,
+        )
+        .unwrap();
+
+        for (path, round_id, ongoing) in [
+            ("2024/01/01/100/game.log", 100, true),
+            ("2024/01/01/99/game.log", 99, false),
+        ] {
+            assert_eq!(
+                round_info_for_path(&numeric_config, &round_ids, Path::new(path))
+                    .unwrap()
+                    .map(|round_info| (round_info.round_id, round_info.ongoing)),
+                Some((round_id, ongoing)),
+                "{path}"
+            );
+        }
+
+        let default_config = config(EmptyRoundIdsPolicy::default());
+        assert_eq!(
+            round_info_for_path(
+                &default_config,
+                &round_ids,
+                Path::new("2024/01/01/100/game.log")
+            )
+            .unwrap(),
+            None
+        );
+
+        assert!(toml::from_str::<OngoingRoundProtectionConfig>(
+            r#"
+                serverinfo = "https://example.com/serverinfo.json"
+                round_folder_pattern = '^[0-9]+$'
+            "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_path_normalization() {
+        let config = config(EmptyRoundIdsPolicy::default());
+        let round_ids = HashMap::from([("sybil".to_owned(), 100)]);
+
+        for path in [
+            "./2024/01/01/round-100/game.log",
+            "2024/./01/01/round-100/./game.log",
+            "2024//01/01//round-100//game.log",
+            "2024/01/01/round-100/",
+            "2024/01/01/round-99/../round-100/game.log",
+            "2024/01/01/round-100/game.log/..",
+        ] {
+            assert!(
+                path_is_ongoing_round(&config, &round_ids, Path::new(path)).unwrap(),
+                "{path}"
+            );
+        }
+
+        assert!(!path_is_ongoing_round(
+            &config,
+            &round_ids,
+            Path::new("2024/01/01/round-100/../round-99/game.log")
+        )
+        .unwrap());
+
+        assert_eq!(
+            normalize_path(Path::new("../a/./b/../c/")),
+            PathBuf::from("../a/c")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_identifiers_keep_highest() {
+        for server_info in [
+            r#"{ "servers": [
+                { "data": { "identifier": "sybil", "round_id": "200" } },
+                { "data": { "identifier": "sybil", "round_id": "150" } },
+                { "data": { "identifier": "manuel", "round_id": "50" } }
+            ] }"#,
+            r#"{ "servers": [
+                { "data": { "identifier": "sybil", "round_id": "150" } },
+                { "data": { "identifier": "sybil", "round_id": "200" } },
+                { "data": { "identifier": "manuel", "round_id": "50" } }
+            ] }"#,
+        ] {
+            assert_eq!(
+                round_ids_from_server_info(server_info.as_bytes()).unwrap(),
+                HashMap::from([("sybil".to_owned(), 200), ("manuel".to_owned(), 50)])
+            );
+        }
+    }
+
+    #[test]
+    fn test_composite_paths_to_identifiers() {
+        // Both servers are on a round 12345, but only sybil's is still going
+        let round_ids = HashMap::from([("sybil".to_owned(), 12345), ("manuel".to_owned(), 12400)]);
+        let config = OngoingRoundProtectionConfig {
+            paths_to_identifiers: Some(HashMap::from([
+                ("sybil/round-12345".to_owned(), "sybil".to_owned()),
+                ("manuel/round-12345".to_owned(), "manuel".to_owned()),
+                ("round-500".to_owned(), "sybil".to_owned()),
+            ])),
+            ..config(EmptyRoundIdsPolicy::default())
+        };
+
+        assert_eq!(
+            round_info_for_path(
+                &config,
+                &round_ids,
+                Path::new("sybil/2024/01/02/round-12345/game.log")
+            )
+            .unwrap(),
+            Some(RoundInfo {
+                round_id: 12345,
+                identifier: Some("sybil".to_owned()),
+                ongoing: true,
+            })
+        );
+
+        assert_eq!(
+            round_info_for_path(
+                &config,
+                &round_ids,
+                Path::new("manuel/2024/01/02/round-12345/game.log")
+            )
+            .unwrap(),
+            Some(RoundInfo {
+                round_id: 12345,
+                identifier: Some("manuel".to_owned()),
+                ongoing: false,
+            })
+        );
+
+        // The parent folder works the same as one further up
+        assert!(
+            path_is_ongoing_round(&config, &round_ids, Path::new("sybil/round-12345")).unwrap()
+        );
+
+        // Plain round folder names still work
+        assert_eq!(
+            round_info_for_path(&config, &round_ids, Path::new("terry/round-500/game.log"))
+                .unwrap()
+                .and_then(|round_info| round_info.identifier),
+            Some("sybil".to_owned())
+        );
+
+        // Not in the map at all, so not ongoing, the same as before
+        assert!(
+            !path_is_ongoing_round(&config, &round_ids, Path::new("terry/round-12345")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_repeated_failures() {
+        let mut failures = RepeatedFailures::default();
+        let start = Instant::now();
+
+        assert_eq!(
+            failures.record_failure("timed out", start).as_deref(),
+            Some("timed out")
+        );
+
+        // Logged again on every tenth repeat
+        let logged = (2..=21)
+            .filter_map(|_| failures.record_failure("timed out", start))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            logged,
+            vec![
+                "timed out (11 times in a row)".to_owned(),
+                "timed out (21 times in a row)".to_owned(),
+            ]
+        );
+
+        // Or once enough time has passed
+        assert_eq!(failures.record_failure("timed out", start), None);
+        assert_eq!(
+            failures
+                .record_failure("timed out", start + RepeatedFailures::REPEAT_LOG_INTERVAL)
+                .as_deref(),
+            Some("timed out (23 times in a row)")
+        );
+
+        // A different error is logged right away
+        failures.record_failure("timed out", start);
+        assert_eq!(
+            failures.record_failure("404", start).as_deref(),
+            Some("404 (after 1 more of the previous error)")
+        );
+
+        assert_eq!(failures.record_success(), Some(25));
+        assert_eq!(failures.record_success(), None);
+        assert_eq!(
+            failures.record_failure("404", start).as_deref(),
+            Some("404")
+        );
+    }
+
+    #[test]
+    fn test_no_active_round() {
+        let round_ids = round_ids_from_server_info(
+            br#"{ "servers": [
+                { "data": { "identifier": "sybil", "round_id": "0" } },
+                { "data": { "identifier": "manuel", "round_id": "-1" } },
+                { "data": { "identifier": "terry", "round_id": "300" } }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            round_ids,
+            HashMap::from([
+                ("sybil".to_owned(), NO_ACTIVE_ROUND),
+                ("manuel".to_owned(), NO_ACTIVE_ROUND),
+                ("terry".to_owned(), 300),
+            ])
+        );
+
+        assert_eq!(
+            round_ids_from_flat_map(br#"{"sybil": 0, "manuel": -1, "terry": "0"}"#).unwrap(),
+            HashMap::from([
+                ("sybil".to_owned(), NO_ACTIVE_ROUND),
+                ("manuel".to_owned(), NO_ACTIVE_ROUND),
+                ("terry".to_owned(), NO_ACTIVE_ROUND),
+            ])
+        );
+
+        assert!(round_ids_from_server_info(
+            br#"{ "servers": [{ "data": { "identifier": "sybil", "round_id": "abc" } }] }"#
+        )
+        .is_err());
+
+        // Without paths_to_identifiers, a round folder is only ongoing if its id matches, and none are 0
+        let config = config(EmptyRoundIdsPolicy::default());
+        for path in ["2024/01/01/round-0/game.log", "2024/01/01/round-1/game.log"] {
+            assert!(!path_is_ongoing_round(&config, &round_ids, Path::new(path)).unwrap());
+        }
+
+        // With it, nothing from that server is ongoing, rather than every round being at least round 0
+        let config = OngoingRoundProtectionConfig {
+            paths_to_identifiers: Some(HashMap::from([
+                ("sybil-1".to_owned(), "sybil".to_owned()),
+                ("terry-301".to_owned(), "terry".to_owned()),
+            ])),
+            round_folder_pattern: toml::from_str::<OngoingRoundProtectionConfig>(
+                r#"
+                    serverinfo = "https://example.com/serverinfo.json"
+                    round_folder_pattern = '^[a-z]+-([0-9]+)$'
+                "#,
+            )
+            .unwrap()
+            .round_folder_pattern,
+            ..config
+        };
+        assert!(
+            !path_is_ongoing_round(&config, &round_ids, Path::new("sybil-1/game.log")).unwrap()
+        );
+        assert!(
+            path_is_ongoing_round(&config, &round_ids, Path::new("terry-301/game.log")).unwrap()
+        );
+    }
+
+    fn round_ids_after_empty_serverinfo(policy: EmptyRoundIdsPolicy) -> HashMap<String, u64> {
+        let mut last_known_round_ids = HashMap::from([("sybil".to_owned(), 100)]);
+
+        let round_ids = round_ids_from_server_info(EMPTY_SERVER_INFO.as_bytes()).unwrap();
+        assert!(round_ids.is_empty());
+
+        update_round_ids(policy, &mut last_known_round_ids, round_ids);
+        last_known_round_ids
+    }
+
+    #[test]
+    fn test_empty_serverinfo_keep_previous() {
+        let config = config(EmptyRoundIdsPolicy::KeepPrevious);
+        let round_ids = round_ids_after_empty_serverinfo(config.empty_round_ids);
+
+        assert_eq!(round_ids.get("sybil"), Some(&100));
+        assert!(
+            path_is_ongoing_round(&config, &round_ids, Path::new("2024/01/01/round-100")).unwrap()
+        );
+        assert!(
+            !path_is_ongoing_round(&config, &round_ids, Path::new("2024/01/01/round-99")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_serverinfo_all_safe() {
+        let config = config(EmptyRoundIdsPolicy::AllSafe);
+        let round_ids = round_ids_after_empty_serverinfo(config.empty_round_ids);
+
+        assert!(round_ids.is_empty());
+        assert!(
+            !path_is_ongoing_round(&config, &round_ids, Path::new("2024/01/01/round-100")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_serverinfo_all_ongoing() {
+        let config = config(EmptyRoundIdsPolicy::AllOngoing);
+        let round_ids = round_ids_after_empty_serverinfo(config.empty_round_ids);
+
+        assert!(round_ids.is_empty());
+        assert!(
+            path_is_ongoing_round(&config, &round_ids, Path::new("2024/01/01/round-1")).unwrap()
+        );
+        assert!(!path_is_ongoing_round(&config, &round_ids, Path::new("2024/01/01")).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_shared_client_concurrency_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let serverinfo = format!("http://{}/serverinfo.json", listener.local_addr().unwrap());
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most_in_flight = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn({
+            let in_flight = Arc::clone(&in_flight);
+            let most_in_flight = Arc::clone(&most_in_flight);
+
+            async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let in_flight = Arc::clone(&in_flight);
+                    let most_in_flight = Arc::clone(&most_in_flight);
+
+                    tokio::spawn(async move {
+                        let mut request = [0; 1024];
+                        let _ = stream.read(&mut request).await.unwrap();
+
+                        let now_in_flight = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        most_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                        let body =
+                            r#"{"servers":[{"data":{"identifier":"sybil","round_id":"5"}}]}"#;
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                            body.len()
+                        );
+                        stream.write_all(response.as_bytes()).await.unwrap();
+                    });
+                }
+            }
+        });
+
+        let client = ServerInfoClient::new(reqwest::Client::new()).with_concurrency_limit(1);
+        let protections = (0..3)
+            .map(|_| {
+                let config = toml::from_str(&format!("serverinfo = {serverinfo:?}")).unwrap();
+                OngoingRoundProtection::with_client(config, client.clone()).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let round_ids = tokio::join!(
+            protections[0].fetch_round_ids(),
+            protections[1].fetch_round_ids(),
+            protections[2].fetch_round_ids(),
+        );
+
+        for round_ids in [round_ids.0, round_ids.1, round_ids.2] {
+            assert_eq!(round_ids.unwrap(), HashMap::from([("sybil".to_owned(), 5)]));
+        }
+        assert_eq!(most_in_flight.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_round_ids_from_flat_map() {
+        assert_eq!(
+            round_ids_from_flat_map(br#"{"sybil": 100, "manuel": "200", "terry": null}"#).unwrap(),
+            HashMap::from([("sybil".to_owned(), 100), ("manuel".to_owned(), 200)])
+        );
+
+        assert!(round_ids_from_flat_map(br#"{"sybil": "abc"}"#).is_err());
+        assert!(round_ids_from_flat_map(br#"{"servers": []}"#).is_err());
+    }
+
+    #[derive(Debug)]
+    struct FixedRoundSource(HashMap<String, u64>);
+
+    impl RoundSource for FixedRoundSource {
+        fn current_round_ids(&self) -> RoundIdsFuture<'_> {
+            Box::pin(async move { Ok(self.0.clone()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let protection = OngoingRoundProtection::with_round_source(
+            config(EmptyRoundIdsPolicy::default()),
+            Arc::new(FixedRoundSource(HashMap::from([
+                ("sybil".to_owned(), 100),
+                ("manuel".to_owned(), 200),
+            ]))),
+        )
+        .unwrap();
+
+        assert_eq!(
+            protection.health().await,
+            ProtectionHealth {
+                initialized: false,
+                last_success: None,
+                known_count: 0,
+            }
+        );
+
+        let before = Instant::now();
+        protection.snapshot().await.unwrap();
+
+        let health = protection.health().await;
+        assert!(health.initialized);
+        assert_eq!(health.known_count, 2);
+        assert!(health
+            .last_success
+            .is_some_and(|last_success| last_success >= before));
+    }
+
+    #[tokio::test]
+    async fn test_custom_round_source() {
+        let protection = OngoingRoundProtection::with_round_source(
+            config(EmptyRoundIdsPolicy::default()),
+            Arc::new(FixedRoundSource(HashMap::from([("sybil".to_owned(), 100)]))),
+        )
+        .unwrap();
+
+        assert!(protection
+            .path_is_ongoing_round(Path::new("2024/01/01/round-100/game.log"))
+            .await
+            .unwrap());
+        assert!(!protection
+            .path_is_ongoing_round(Path::new("2024/01/01/round-99/game.log"))
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn test_serverinfo_format() {
+        let config: OngoingRoundProtectionConfig = toml::from_str(
+            r#"
+                serverinfo = "https://example.com/round_ids.json"
+                serverinfo_format = "flat_map"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.serverinfo_format, ServerInfoFormat::FlatMap);
+
+        let protection = OngoingRoundProtection::new(config).unwrap();
+        assert!(format!("{:?}", protection.round_source).contains("FlatMapRoundSource"));
+    }
+}
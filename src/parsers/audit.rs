@@ -0,0 +1,85 @@
+// For keeping a separate, access controlled record of exactly what was censored,
+// while only the sanitized version is published.
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct AuditRecord<'a> {
+    pub file: &'a str,
+    // Starting from 1
+    pub line: usize,
+    pub reason: &'a str,
+    pub original: &'a str,
+}
+
+pub trait AuditSink {
+    fn record(&mut self, record: AuditRecord<'_>);
+}
+
+impl<F: FnMut(AuditRecord<'_>)> AuditSink for F {
+    fn record(&mut self, record: AuditRecord<'_>) {
+        self(record)
+    }
+}
+
+// Writes every record as a line of JSON.
+// Sanitizing carries on if writing fails, so the first error is kept until finish.
+pub struct JsonlAuditWriter<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JsonlAuditWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> AuditSink for JsonlAuditWriter<W> {
+    fn record(&mut self, record: AuditRecord<'_>) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(error) = serde_json::to_writer(&mut self.writer, &record)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"))
+        {
+            self.error = Some(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsonl_audit_writer() {
+        let mut writer = JsonlAuditWriter::new(Vec::new());
+
+        writer.record(AuditRecord {
+            file: "round-1/game.log",
+            line: 3,
+            reason: "ip",
+            original: "1.2.3.4",
+        });
+
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(
+            output,
+            "{\"file\":\"round-1/game.log\",\"line\":3,\"reason\":\"ip\",\"original\":\"1.2.3.4\"}\n"
+        );
+    }
+}
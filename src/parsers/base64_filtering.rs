@@ -0,0 +1,54 @@
+use std::{borrow::Cow, sync::LazyLock};
+
+use regex::{Captures, Regex};
+
+static BASE64_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9+/]+={0,2}").unwrap());
+
+// Long runs of base64 characters, which players and tools sometimes paste whole files as.
+// Real base64 of any length is a mix of upper case, lower case, and digits,
+// so long ordinary words and numbers aren't caught.
+pub fn filter_base64_blobs(contents: &str, min_length: usize) -> Cow<'_, str> {
+    BASE64_REGEX.replace_all(contents, |captures: &Captures| {
+        let run = &captures[0];
+
+        if run.len() >= min_length
+            && run.contains(|c: char| c.is_ascii_uppercase())
+            && run.contains(|c: char| c.is_ascii_lowercase())
+            && run.contains(|c: char| c.is_ascii_digit())
+        {
+            "-censored(base64 blob)-".to_owned()
+        } else {
+            run.to_owned()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_base64_blobs() {
+        assert_eq!(
+            filter_base64_blobs(
+                "Somebody wrote eyJuYW1lIjoiU29tZSBCb2R5IiwiaXAiOiIxLjIuMy40In0= on the paper",
+                32
+            ),
+            "Somebody wrote -censored(base64 blob)- on the paper"
+        );
+    }
+
+    #[test]
+    fn test_long_normal_tokens_untouched() {
+        for line in [
+            "Supercalifragilisticexpialidocious antidisestablishmentarianism",
+            "/obj/item/clothing/under/rank/security/officer/formal",
+            "12345678901234567890123456789012345678901234567890",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "short aGVsbG8= blob",
+        ] {
+            assert_eq!(filter_base64_blobs(line, 32), line);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// signal.log and telecomms.log are radio and PDA traffic, where the message itself is whatever a player
+// said. Operators can have the message censored while keeping who sent it, on what frequency, and where.
+// The message is the quoted part of a line, like: [timestamp] TCOMMS: Some Body (Captain) [Common] "hello" (Bridge (1,2,3))
+// so everything from the first quote to the last is censored, and anything after it is kept.
+use std::borrow::Cow;
+
+use super::player_content::{censor_json_lines, censor_lines, filter_uncensored};
+
+const CENSORED_CONTENT: &str = "-censored(comms message)-";
+
+// Keys in a .log.json entry's data that hold what was said, rather than where it went
+const MESSAGE_KEYS: &[&str] = &["message", "text", "content"];
+
+#[derive(Clone, Debug, Default)]
+pub struct CommsLogPolicy {
+    // Without this, comms logs are passed through like any other log, with IPs and keywords filtered
+    pub censor_messages: bool,
+}
+
+pub fn process_comms_log_with_policy(
+    contents: &str,
+    policy: &CommsLogPolicy,
+    keyword_blocklist: &[String],
+) -> String {
+    if !policy.censor_messages {
+        return filter_uncensored(contents, keyword_blocklist);
+    }
+
+    censor_lines(
+        contents,
+        keyword_blocklist,
+        CENSORED_CONTENT,
+        censor_message,
+    )
+}
+
+// The .log.json variants have one JSON object per line, with the line in "message" and its parts in "data"
+pub fn process_comms_log_json_with_policy(
+    contents: &str,
+    policy: &CommsLogPolicy,
+    keyword_blocklist: &[String],
+) -> String {
+    if !policy.censor_messages {
+        return filter_uncensored(contents, keyword_blocklist);
+    }
+
+    censor_json_lines(
+        contents,
+        keyword_blocklist,
+        censor_message,
+        censor_message_keys,
+    )
+}
+
+fn censor_message(text: &str) -> Cow<'_, str> {
+    let Some(message_start) = text.find('"') else {
+        return Cow::Borrowed(text);
+    };
+
+    // An unclosed quote could be the start of a message that goes onto the next line
+    let message_end = match text.rfind('"') {
+        Some(message_end) if message_end > message_start => message_end + 1,
+        _ => text.len(),
+    };
+
+    Cow::Owned(format!(
+        "{}{CENSORED_CONTENT}{}",
+        &text[..message_start],
+        &text[message_end..]
+    ))
+}
+
+fn censor_message_keys(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Array(values) => values.iter_mut().for_each(censor_message_keys),
+        serde_json::Value::Object(entries) => {
+            for (key, value) in entries.iter_mut() {
+                if MESSAGE_KEYS.contains(&key.as_str()) {
+                    *value = serde_json::Value::String(CENSORED_CONTENT.to_owned());
+                } else {
+                    censor_message_keys(value);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TELECOMMS_LOG: &str = "\
+[2024-01-02 03:04:05.678] TCOMMS: Somebody/(Some Body) from 1.2.3.4 [Common] (1459) \"call me at 555-1234\" (Bridge (1,2,3))
+[2024-01-02 03:04:06.000] TCOMMS: Somebody/(Some Body) [Security] (1359) \"multiple
+lines\" (Brig (4,5,6))
+[2024-01-02 03:04:07.000] SIGNAL: Somebody/(Some Body) pulsed the door wire
+";
+
+    #[test]
+    fn test_passthrough_by_default() {
+        assert_eq!(
+            process_comms_log_with_policy(TELECOMMS_LOG, &CommsLogPolicy::default(), &[]),
+            TELECOMMS_LOG.replace("1.2.3.4", "-censored-")
+        );
+
+        assert_eq!(
+            process_comms_log_with_policy(
+                TELECOMMS_LOG,
+                &CommsLogPolicy::default(),
+                &["door wire".to_owned()]
+            ),
+            TELECOMMS_LOG
+                .replace("1.2.3.4", "-censored-")
+                .replace("door wire", "-censored(keyword)-")
+        );
+    }
+
+    #[test]
+    fn test_censor_messages() {
+        let policy = CommsLogPolicy {
+            censor_messages: true,
+        };
+
+        assert_eq!(
+            process_comms_log_with_policy(TELECOMMS_LOG, &policy, &["somebody".to_owned()]),
+            "\
+[2024-01-02 03:04:05.678] TCOMMS: -censored(keyword)-/(Some Body) from -censored- [Common] (1459) -censored(comms message)- (Bridge (1,2,3))
+[2024-01-02 03:04:06.000] TCOMMS: -censored(keyword)-/(Some Body) [Security] (1359) -censored(comms message)-
+-censored(comms message)-
+[2024-01-02 03:04:07.000] SIGNAL: -censored(keyword)-/(Some Body) pulsed the door wire
+"
+        );
+    }
+
+    #[test]
+    fn test_censor_json_messages() {
+        let policy = CommsLogPolicy {
+            censor_messages: true,
+        };
+
+        let contents = r#"{"ts":"2024-01-02 03:04:05.678","category":"TCOMMS","message":"Somebody [Common] \"hello from 1.2.3.4\" (Bridge)","data":{"frequency":1459,"speaker":"Somebody","message":"hello from 1.2.3.4"}}
+not json
+"#;
+
+        assert_eq!(
+            process_comms_log_json_with_policy(contents, &policy, &[]),
+            r#"{"category":"TCOMMS","data":{"frequency":1459,"message":"-censored(comms message)-","speaker":"Somebody"},"message":"Somebody [Common] -censored(comms message)- (Bridge)","ts":"2024-01-02 03:04:05.678"}
+{"censored":"invalid json"}
+"#
+        );
+
+        assert_eq!(
+            process_comms_log_json_with_policy(contents, &CommsLogPolicy::default(), &[]),
+            contents.replace("1.2.3.4", "-censored-")
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::{borrow::Cow, sync::LazyLock};
+
+use regex::Regex;
+
+// Tile coordinates as BYOND prints them, like (123, 45, 2) or (123,45,2)
+static COORDINATES_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\(\s*[0-9]{1,3}\s*,\s*[0-9]{1,3}\s*,\s*[0-9]{1,2}\s*\)").unwrap()
+});
+
+pub fn filter_coordinates(contents: &str) -> Cow<'_, str> {
+    COORDINATES_REGEX.replace_all(contents, "-censored(coords)-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_coordinates() {
+        assert_eq!(
+            filter_coordinates("Somebody built a wall at Bar (123, 45, 2)"),
+            "Somebody built a wall at Bar -censored(coords)-"
+        );
+        assert_eq!(
+            filter_coordinates("the floor (100,100,2) (/turf/open/floor)"),
+            "the floor -censored(coords)- (/turf/open/floor)"
+        );
+    }
+
+    #[test]
+    fn test_ordinary_numbers_untouched() {
+        for line in [
+            "paid 123, 45, 2 credits",
+            "a pair (12, 34)",
+            "too big (1234, 5, 6)",
+            "four of them (1, 2, 3, 4)",
+            "version 515.1630",
+        ] {
+            assert_eq!(filter_coordinates(line), line);
+        }
+    }
+}
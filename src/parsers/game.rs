@@ -1,8 +1,13 @@
-use std::{borrow::Cow, sync::LazyLock};
+use std::{borrow::Cow, collections::HashMap, sync::LazyLock};
 
 use regex::{Regex, RegexSet};
 
-use super::ip_filtering::filter_ips;
+use super::{
+    audit::{AuditRecord, AuditSink},
+    ip_filtering::{filter_ips_in, find_ips, IpCensorContext},
+    strip_bom,
+    url_filtering::{filter_urls, UrlFiltering},
+};
 
 // A macro to allow for &'static str returns
 macro_rules! censor {
@@ -11,78 +16,673 @@ macro_rules! censor {
     };
 }
 
-#[tracing::instrument(skip_all)]
+#[derive(Clone, Debug)]
+pub struct GameLogPolicy {
+    pub url_filtering: UrlFiltering,
+
+    // Lines longer than this (in bytes) are censored without being parsed
+    pub max_line_length: Option<usize>,
+
+    // What to do with log types that don't have any special handling
+    pub unknown_policy: UnknownPolicy,
+
+    // Censor reverse DNS hostnames that some servers log next to the IP in ACCESS logins
+    pub filter_hostnames: bool,
+
+    // ADMINPRIVATE messages starting with any of these are published, the rest are still censored
+    pub adminprivate_allowlist: Vec<String>,
+
+    // Treat lines that don't start with a timestamp as part of the line before them,
+    // keeping or censoring them along with it, rather than always censoring them.
+    pub continuation_lines: bool,
+
+    // Censor the value of account=<n> and ID=<n> tokens, which are stable per player
+    pub filter_account_ids: bool,
+
+    // Only accept the full "2024-01-02 03:04:05.678" timestamp, not the older or shortened ones
+    pub require_full_timestamps: bool,
+
+    // Log types (without the colon or a GAME- prefix) that are published exactly as they are
+    // once the timestamp checks out, skipping every other rule for speed.
+    // IPs are still filtered over the whole file before any line is parsed, unless ip_free_log_types says otherwise.
+    pub safe_log_types: Vec<String>,
+
+    // Log types (without the colon or a GAME- prefix) that rarely have IPs, for speed. Their lines get a quick
+    // scan instead of the IP regex, and only go through it if the scan finds something that could be one,
+    // so the output is the same either way. ACCESS lines, and any line whose type can't be told before
+    // parsing, always go through it.
+    pub ip_free_log_types: Vec<String>,
+
+    // Censor IPs as -censored(ip:game)- rather than -censored-, to tell where they were censored
+    pub label_ip_censors: bool,
+
+    // Censor the values of these keys, at any depth, in a JSON object at the end of a kept line.
+    // Something like ckey, ip, address, and computer_id. The object is reserialized, so its formatting can change.
+    pub embedded_json_keys: Vec<String>,
+
+    // Publish a GAME-COMPAT: line with nothing after it as it is, for servers that log those as markers,
+    // rather than censoring it as game_compat_no_followup
+    pub allow_empty_game_compat: bool,
+}
+
+impl Default for GameLogPolicy {
+    fn default() -> Self {
+        Self {
+            url_filtering: UrlFiltering::default(),
+            max_line_length: Some(64 * 1024),
+            unknown_policy: UnknownPolicy::default(),
+            filter_hostnames: true,
+            adminprivate_allowlist: Vec::new(),
+            continuation_lines: false,
+            filter_account_ids: true,
+            require_full_timestamps: false,
+            safe_log_types: Vec::new(),
+            ip_free_log_types: Vec::new(),
+            label_ip_censors: false,
+            embedded_json_keys: Vec::new(),
+            allow_empty_game_compat: false,
+        }
+    }
+}
+
+impl GameLogPolicy {
+    // The game log half of StrictMode, see there for what it changes
+    pub fn strict() -> Self {
+        let mut policy = Self::default();
+        policy.apply_strict_mode();
+        policy
+    }
+
+    pub fn apply_strict_mode(&mut self) {
+        self.unknown_policy = UnknownPolicy::Censor;
+        self.require_full_timestamps = true;
+        self.continuation_lines = false;
+        self.adminprivate_allowlist.clear();
+        self.safe_log_types.clear();
+        self.allow_empty_game_compat = false;
+        self.url_filtering = UrlFiltering::Censor;
+    }
+
+    fn ip_censor_context(&self) -> Option<IpCensorContext> {
+        self.label_ip_censors.then_some(IpCensorContext::Game)
+    }
+
+    fn is_too_long(&self, line: &str) -> bool {
+        self.max_line_length
+            .is_some_and(|max_line_length| line.len() > max_line_length)
+    }
+
+    fn is_safe_log_type(&self, log_type: &str) -> bool {
+        let log_type = log_type.trim_start_matches("GAME-");
+        self.safe_log_types.iter().any(|safe| safe == log_type)
+    }
+
+    fn is_ip_free_log_type(&self, log_type: &str) -> bool {
+        let log_type = log_type.trim_start_matches("GAME-");
+        log_type != "ACCESS"
+            && self
+                .ip_free_log_types
+                .iter()
+                .any(|ip_free| ip_free == log_type)
+    }
+
+    // Filters IPs out of all of contents, skipping the regex for lines of an IP free type that can't have one.
+    // Every game log entry point goes through this, so they all censor the same.
+    fn filter_ips<'a>(&self, contents: &'a str) -> Cow<'a, str> {
+        if self.ip_free_log_types.is_empty() {
+            return filter_ips_in(contents, self.ip_censor_context());
+        }
+
+        let mut filtered = String::new();
+        let mut changed = false;
+
+        // Runs of lines that need filtering are filtered together, since a regex search per line adds up
+        let mut push_filtered = |run: &str, filtered: &mut String| {
+            let run = filter_ips_in(run, self.ip_censor_context());
+            changed |= matches!(run, Cow::Owned(_));
+            filtered.push_str(&run);
+        };
+
+        let mut run_start = 0;
+        let mut offset = 0;
+        for line in contents.split_inclusive('\n') {
+            if split_line_log_type(line).is_some_and(|(log_type, message)| {
+                self.is_ip_free_log_type(log_type) && !may_contain_ip(message)
+            }) {
+                push_filtered(&contents[run_start..offset], &mut filtered);
+                filtered.push_str(line);
+                run_start = offset + line.len();
+            }
+
+            offset += line.len();
+        }
+        push_filtered(&contents[run_start..], &mut filtered);
+
+        match changed {
+            true => Cow::Owned(filtered),
+            false => Cow::Borrowed(contents),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownPolicy {
+    #[default]
+    Allow,
+    Censor,
+}
+
+// The whole line is censored, with the reason kept around for anything that wants it
+macro_rules! censored {
+    ($kind:literal) => {
+        ParsedLine::Censored {
+            reason: $kind,
+            text: censor!($kind),
+        }
+    };
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParsedLine<'a> {
+    Kept {
+        // Without the surrounding brackets
+        timestamp: &'a str,
+        // Without the trailing colon, and None for lines without one like the round start
+        log_type: Option<&'a str>,
+        message: Cow<'a, str>,
+        // The full line as it should be published
+        line: Cow<'a, str>,
+    },
+
+    Censored {
+        reason: &'static str,
+        text: &'static str,
+    },
+}
+
+impl<'a> ParsedLine<'a> {
+    pub fn into_text(self) -> Cow<'a, str> {
+        match self {
+            ParsedLine::Kept { line, .. } => line,
+            ParsedLine::Censored { text, .. } => Cow::Borrowed(text),
+        }
+    }
+}
+
 pub fn parse_line<'a>(line: &'a str) -> Cow<'a, str> {
-    let line = line.trim();
+    parse_line_with_policy(line, &GameLogPolicy::default())
+}
+
+pub fn parse_line_with_policy<'a>(line: &'a str, policy: &GameLogPolicy) -> Cow<'a, str> {
+    parse_line_structured(line, policy).into_text()
+}
+
+// Splits off the next space separated word, and whatever is after it if there was a space
+fn next_word(text: &str) -> (&str, Option<&str>) {
+    match text.split_once(' ') {
+        Some((word, rest)) => (word, Some(rest)),
+        None => (text, None),
+    }
+}
+
+// Deliberately strict so that dotted things like "v515.1630" or "thing.dm" aren't caught.
+// Needs at least three labels and an alphabetic top level domain, like host-1-2.isp.example.com.
+fn is_hostname(word: &str) -> bool {
+    static HOSTNAME_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.){2,}[a-z]{2,24}$").unwrap()
+    });
+
+    HOSTNAME_REGEX.is_match(word.trim_matches(|c| matches!(c, '(' | ')' | '[' | ']' | ',')))
+}
+
+pub fn parse_line_structured<'a>(line: &'a str, policy: &GameLogPolicy) -> ParsedLine<'a> {
+    parse_line_inner(line, policy, &mut |_, _| {})
+}
+
+// Like parse_line_structured, but calls on_censor with the reason and original text of everything it censors,
+// whether that's the whole line or only part of it.
+pub fn parse_line_audited<'a>(
+    line: &'a str,
+    policy: &GameLogPolicy,
+    on_censor: &mut dyn FnMut(&'static str, &str),
+) -> ParsedLine<'a> {
+    let parsed = parse_line_inner(line, policy, on_censor);
+
+    if let ParsedLine::Censored { reason, .. } = parsed {
+        on_censor(reason, line);
+    }
+
+    parsed
+}
+
+// What happened to the last line, for continuation_lines
+#[derive(Clone, Copy)]
+enum Disposition<'a> {
+    Kept {
+        timestamp: &'a str,
+        log_type: Option<&'a str>,
+    },
+
+    Censored {
+        reason: &'static str,
+        text: &'static str,
+    },
+}
+
+// Parses the lines of one file in order, since with continuation_lines a line depends on the one before it
+struct GameLogParser<'p, 'a> {
+    policy: &'p GameLogPolicy,
+    previous: Option<Disposition<'a>>,
+}
+
+impl<'p, 'a> GameLogParser<'p, 'a> {
+    fn new(policy: &'p GameLogPolicy) -> Self {
+        Self {
+            policy,
+            previous: None,
+        }
+    }
+
+    fn parse_line(
+        &mut self,
+        line: &'a str,
+        on_censor: &mut dyn FnMut(&'static str, &str),
+    ) -> ParsedLine<'a> {
+        let trimmed = line.trim();
+
+        // Blank lines are censored as usual, but don't interrupt a multi-line message
+        if trimmed.is_empty() {
+            return parse_line_audited(line, self.policy, on_censor);
+        }
+
+        if self.policy.continuation_lines && !trimmed.starts_with('[') {
+            // Doesn't end the message it's continuing, so the lines after it can still be kept
+            if self.policy.is_too_long(trimmed) {
+                on_censor("line_too_long", line);
+                return censored!("line_too_long");
+            }
+
+            match self.previous {
+                Some(Disposition::Kept {
+                    timestamp,
+                    log_type,
+                }) => {
+                    // Published with its indentation, which can be part of the message's formatting
+                    let continued = ParsedLine::Kept {
+                        timestamp,
+                        log_type,
+                        message: Cow::Borrowed(trimmed),
+                        line: Cow::Borrowed(line.trim_end()),
+                    };
+
+                    return censor_kept_line(continued, self.policy, on_censor);
+                }
+
+                Some(Disposition::Censored { reason, text }) => {
+                    on_censor(reason, line);
+                    return ParsedLine::Censored { reason, text };
+                }
+
+                // Nothing to continue, so it's censored like normal
+                None => {}
+            }
+        }
+
+        let parsed = parse_line_audited(line, self.policy, on_censor);
+
+        self.previous = Some(match parsed {
+            ParsedLine::Kept {
+                timestamp,
+                log_type,
+                ..
+            } => Disposition::Kept {
+                timestamp,
+                log_type,
+            },
+
+            ParsedLine::Censored { reason, text } => Disposition::Censored { reason, text },
+        });
+
+        parsed
+    }
+}
+
+static TIMESTAMP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^([0-9]{2}:[0-9]{2}:[0-9]{2}|[0-9]{2,4}-[0-9]{2,4}-[0-9]{2,4} [0-9]{2}:[0-9]{2}:[0-9]{2}(\.[0-9]{1,3})*)$",
+    ).unwrap()
+});
+
+// Without the brackets, None if the line doesn't start with one parse_line would accept
+fn line_timestamp(line: &str) -> Option<&str> {
+    split_line_timestamp(line).map(|(timestamp, _)| timestamp)
+}
+
+// The timestamp without the brackets, and everything after the closing bracket of the trimmed line
+fn split_line_timestamp(line: &str) -> Option<(&str, &str)> {
+    let (timestamp, rest) = line.trim().strip_prefix('[')?.split_once(']')?;
+    TIMESTAMP_REGEX
+        .is_match(timestamp)
+        .then_some((timestamp, rest))
+}
+
+// The log type without the colon and the message after it, None if it isn't a plain "[...] TYPE: " line.
+// The timestamp isn't checked, since this is only a quick look before parsing, and parsing censors the line if it's bad.
+fn split_line_log_type(line: &str) -> Option<(&str, &str)> {
+    let (timestamp, rest) = line.trim_start().strip_prefix('[')?.split_once("] ")?;
+    if timestamp.contains(['[', ']']) {
+        return None;
+    }
+
+    let (log_type, message) = rest.split_once(' ')?;
+
+    Some((log_type.strip_suffix(':')?, message))
+}
+
+// Every IP has a digit right before a dot, so anything without one can skip the IP regex
+fn may_contain_ip(text: &str) -> bool {
+    text.match_indices('.')
+        .any(|(index, _)| index > 0 && text.as_bytes()[index - 1].is_ascii_digit())
+}
+
+// A logging bug sometimes writes the timestamp more than once, like "[ts][ts] GAME: ...".
+// Everything but the last is dropped, so the line parses like it was written once.
+fn strip_duplicate_timestamps(mut line: &str) -> &str {
+    while let Some((_, rest)) = split_line_timestamp(line) {
+        // Sometimes with a space between them, like "[ts] [ts] GAME: ..."
+        let rest = rest.trim_start();
+        if line_timestamp(rest).is_none() {
+            break;
+        }
+
+        line = rest;
+    }
+
+    line
+}
+
+// Only the exact "account=123" and "ID=123" shapes, so other numbers are left alone
+static ACCOUNT_ID_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(account|ID)=([0-9]+)\b").unwrap());
+
+fn censor_account_ids<'a>(
+    parsed: ParsedLine<'a>,
+    policy: &GameLogPolicy,
+    on_censor: &mut dyn FnMut(&'static str, &str),
+) -> ParsedLine<'a> {
+    fn filter<'a>(text: Cow<'a, str>) -> Cow<'a, str> {
+        if let Cow::Owned(filtered) =
+            ACCOUNT_ID_REGEX.replace_all(&text, concat!("$1=", censor!("account id")))
+        {
+            return Cow::Owned(filtered);
+        }
+
+        text
+    }
+
+    match parsed {
+        ParsedLine::Kept {
+            timestamp,
+            log_type,
+            message,
+            line,
+        } if policy.filter_account_ids
+            && !log_type.is_some_and(|log_type| policy.is_safe_log_type(log_type)) =>
+        {
+            for captures in ACCOUNT_ID_REGEX.captures_iter(&line) {
+                on_censor("account id", &captures[2]);
+            }
+
+            ParsedLine::Kept {
+                timestamp,
+                log_type,
+                message: filter(message),
+                line: filter(line),
+            }
+        }
+
+        parsed => parsed,
+    }
+}
+
+fn censor_embedded_json<'a>(
+    parsed: ParsedLine<'a>,
+    policy: &GameLogPolicy,
+    on_censor: &mut dyn FnMut(&'static str, &str),
+) -> ParsedLine<'a> {
+    fn censor_keys(
+        value: &mut serde_json::Value,
+        keys: &[String],
+        on_censor: &mut dyn FnMut(&'static str, &str),
+    ) -> bool {
+        match value {
+            serde_json::Value::Object(entries) => {
+                let mut censored = false;
+                for (key, value) in entries.iter_mut() {
+                    if keys.iter().any(|censored_key| censored_key == key) {
+                        on_censor("embedded json", &value.to_string());
+                        *value = serde_json::Value::String(censor!("embedded json").to_owned());
+                        censored = true;
+                    } else {
+                        censored |= censor_keys(value, keys, on_censor);
+                    }
+                }
+                censored
+            }
+
+            serde_json::Value::Array(values) => values.iter_mut().fold(false, |censored, value| {
+                censor_keys(value, keys, on_censor) | censored
+            }),
+
+            _ => false,
+        }
+    }
+
+    match parsed {
+        ParsedLine::Kept {
+            timestamp,
+            log_type,
+            message,
+            line,
+        } if !policy.embedded_json_keys.is_empty()
+            && !log_type.is_some_and(|log_type| policy.is_safe_log_type(log_type)) =>
+        {
+            let censored = trailing_json_object(&line).and_then(|(json_start, mut json)| {
+                censor_keys(&mut json, &policy.embedded_json_keys, on_censor)
+                    .then_some((json_start, json))
+            });
+
+            let Some((json_start, json)) = censored else {
+                return ParsedLine::Kept {
+                    timestamp,
+                    log_type,
+                    message,
+                    line,
+                };
+            };
+
+            let json_text = &line[json_start..];
+            let message = match message.strip_suffix(json_text) {
+                Some(before_json) => Cow::Owned(format!("{before_json}{json}")),
+                None => message,
+            };
+
+            ParsedLine::Kept {
+                timestamp,
+                log_type,
+                message,
+                line: Cow::Owned(format!("{}{json}", &line[..json_start])),
+            }
+        }
+
+        parsed => parsed,
+    }
+}
+
+// The first { that starts a JSON object running to the end of the line
+fn trailing_json_object(line: &str) -> Option<(usize, serde_json::Value)> {
+    if !line.trim_end().ends_with('}') {
+        return None;
+    }
+
+    line.match_indices('{').find_map(|(start, _)| {
+        match serde_json::from_str::<serde_json::Value>(&line[start..]) {
+            Ok(json @ serde_json::Value::Object(_)) => Some((start, json)),
+            _ => None,
+        }
+    })
+}
+
+fn parse_line_inner<'a>(
+    line: &'a str,
+    policy: &GameLogPolicy,
+    on_censor: &mut dyn FnMut(&'static str, &str),
+) -> ParsedLine<'a> {
+    let parsed = parse_line_fields(line, policy, on_censor);
+    censor_kept_line(parsed, policy, on_censor)
+}
+
+// What's censored out of any kept line, wherever in the line it is, including continuation lines
+fn censor_kept_line<'a>(
+    parsed: ParsedLine<'a>,
+    policy: &GameLogPolicy,
+    on_censor: &mut dyn FnMut(&'static str, &str),
+) -> ParsedLine<'a> {
+    let parsed = censor_account_ids(parsed, policy, on_censor);
+    censor_embedded_json(parsed, policy, on_censor)
+}
+
+// Trace level, since a span per line adds up over a whole game.log
+#[tracing::instrument(level = "trace", skip_all)]
+fn parse_line_fields<'a>(
+    line: &'a str,
+    policy: &GameLogPolicy,
+    on_censor: &mut dyn FnMut(&'static str, &str),
+) -> ParsedLine<'a> {
+    let line = strip_duplicate_timestamps(line.trim());
 
     if line.is_empty() {
-        return censor!("empty_line").into();
+        return censored!("empty_line");
+    }
+
+    if policy.is_too_long(line) {
+        return censored!("line_too_long");
     }
 
     if !line.starts_with('[') {
-        return censor!("no_ts_start").into();
+        return censored!("no_ts_start");
     }
 
     let Some((timestamp, contents)) = line.split_once(']') else {
-        return censor!("no_category_colon").into(); // Matching PHP
+        return censored!("no_category_colon"); // Matching PHP
     };
 
-    static TIMESTAMP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-        Regex::new(
-            r"^([0-9]{2}:[0-9]{2}:[0-9]{2}|[0-9]{2,4}-[0-9]{2,4}-[0-9]{2,4} [0-9]{2}:[0-9]{2}:[0-9]{2}(\.[0-9]{1,3})+)$",
-        ).unwrap()
-    });
     if !TIMESTAMP_REGEX.is_match(&timestamp[1..]) {
-        return censor!("no_ts_regex_match").into();
+        return censored!("no_ts_regex_match");
     }
 
-    if contents.starts_with(" Starting up round ID ") {
-        return Cow::Borrowed(line);
+    static FULL_TIMESTAMP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}\.[0-9]{3}$").unwrap()
+    });
+    if policy.require_full_timestamps && !FULL_TIMESTAMP_REGEX.is_match(&timestamp[1..]) {
+        return censored!("no_ts_regex_match");
     }
 
-    let mut words = contents.split(' ');
-    if words.next() != Some("") {
-        return censor!("no_space_after_timestamp").into();
+    if contents.starts_with(" Starting up round ID ") {
+        return ParsedLine::Kept {
+            timestamp: &timestamp[1..],
+            log_type: None,
+            message: Cow::Borrowed(&contents[1..]),
+            line: Cow::Borrowed(line),
+        };
     }
 
-    let log_type = {
-        let next_word = words.next().expect("out of words");
-        if !next_word.ends_with(':') {
-            return censor!("no_category_colon").into();
-        }
+    let Some(contents) = contents.strip_prefix(' ') else {
+        return censored!("no_space_after_timestamp");
+    };
+
+    let (log_type, remaining) = {
+        let (mut log_type, mut remaining) = next_word(contents);
 
-        if next_word == "GAME-COMPAT:" {
-            match words.next() {
-                Some(next_word) => next_word,
-                None => return censor!("game_compat_no_followup").into(),
+        // The wrapped type goes through the same checks as an unwrapped one
+        if log_type == "GAME-COMPAT:" {
+            match remaining {
+                Some(wrapped) => (log_type, remaining) = next_word(wrapped),
+                None if policy.allow_empty_game_compat => {
+                    return ParsedLine::Kept {
+                        timestamp: &timestamp[1..],
+                        log_type: Some("GAME-COMPAT"),
+                        message: Cow::Borrowed(""),
+                        line: Cow::Borrowed(line),
+                    };
+                }
+                None => return censored!("game_compat_no_followup"),
             }
-        } else {
-            next_word
         }
+
+        if !log_type.ends_with(':') {
+            return censored!("no_category_colon");
+        }
+
+        (log_type, remaining)
+    };
+
+    let remaining = remaining.unwrap_or("");
+
+    if policy.is_safe_log_type(&log_type[0..(log_type.len() - 1)]) {
+        return ParsedLine::Kept {
+            timestamp: &timestamp[1..],
+            log_type: Some(&log_type[0..(log_type.len() - 1)]),
+            message: Cow::Borrowed(remaining),
+            line: Cow::Borrowed(line),
+        };
+    }
+
+    let kept = |message: Cow<'a, str>, line: Cow<'a, str>| ParsedLine::Kept {
+        timestamp: &timestamp[1..],
+        log_type: Some(&log_type[0..(log_type.len() - 1)]),
+        message,
+        line,
     };
 
     match log_type[0..(log_type.len() - 1)].trim_start_matches("GAME-") {
-        "ACCESS" => match words.next() {
-            Some("Login:") => {
-                let mut words_vec = words.collect::<Vec<_>>();
+        "ACCESS" => match next_word(remaining) {
+            ("Login:", login) => {
+                let mut words_vec = login.unwrap_or("").split(' ').collect::<Vec<_>>();
 
-                let ip_cid_index = words_vec.len() - 4;
+                // Too short to have the ip/cid where we expect it, so there's no telling where it is
+                let Some(ip_cid_index) = words_vec.len().checked_sub(4) else {
+                    return censored!("malformed_login");
+                };
+                on_censor("ip/cid", words_vec[ip_cid_index]);
                 words_vec[ip_cid_index] = censor!("ip/cid");
 
-                Cow::Owned(format!(
-                    "{timestamp}] {log_type} Login: {}",
-                    words_vec.join(" ")
-                ))
+                if policy.filter_hostnames {
+                    for word in &mut words_vec {
+                        if is_hostname(word) {
+                            on_censor("hostname", word);
+                            *word = censor!("hostname");
+                        }
+                    }
+                }
+
+                let message = format!("Login: {}", words_vec.join(" "));
+                let line = format!("{timestamp}] {log_type} {message}");
+                kept(Cow::Owned(message), Cow::Owned(line))
             }
 
-            Some("Failed") => censor!("invalid connection data").into(),
+            // Keeping that it happened is useful, but the details have the same IPs and CIDs as logins
+            ("Failed", _) => {
+                on_censor("invalid connection data", remaining);
+                let message = concat!("Failed connection ", censor!("invalid connection data"));
+                let line = format!("{timestamp}] {log_type} {message}");
+                kept(Cow::Borrowed(message), Cow::Owned(line))
+            }
 
-            _ => Cow::Borrowed(line),
+            _ => kept(Cow::Borrowed(remaining), Cow::Borrowed(line)),
         },
 
         "ADMIN" => {
-            let remaining = words.collect::<Vec<_>>().join(" ");
-
             static REGEX_SET: LazyLock<RegexSet> = LazyLock::new(|| {
                 RegexSet::new([
                     r"^HELP:",
@@ -99,26 +699,1249 @@ pub fn parse_line<'a>(line: &'a str) -> Cow<'a, str> {
                 .unwrap()
             });
 
-            if REGEX_SET.is_match(&remaining) {
-                return censor!("asay/apm/ahelp/notes/etc").into();
+            if REGEX_SET.is_match(remaining) {
+                return censored!("asay/apm/ahelp/notes/etc");
             }
 
-            Cow::Borrowed(line)
+            kept(Cow::Borrowed(remaining), Cow::Borrowed(line))
         }
 
-        "ADMINPRIVATE" => censor!("private logtype").into(),
+        "ADMINPRIVATE" => {
+            if policy
+                .adminprivate_allowlist
+                .iter()
+                .any(|prefix| remaining.starts_with(prefix.as_str()))
+            {
+                return kept(Cow::Borrowed(remaining), Cow::Borrowed(line));
+            }
+
+            censored!("private logtype")
+        }
 
-        "TOPIC" => censor!("world_topic logs").into(),
+        "TOPIC" => censored!("world_topic logs"),
 
-        "SQL" => censor!("sql logs").into(),
+        "SQL" => censored!("sql logs"),
 
-        _ => Cow::Borrowed(line),
+        _ => match policy.unknown_policy {
+            UnknownPolicy::Allow => kept(Cow::Borrowed(remaining), Cow::Borrowed(line)),
+            UnknownPolicy::Censor => censored!("unknown_category"),
+        },
     }
 }
 
 pub fn process_game_log(contents: String) -> String {
-    filter_ips(&contents)
+    process_game_log_with_policy(contents, &GameLogPolicy::default())
+}
+
+pub fn process_game_log_with_policy(contents: String, policy: &GameLogPolicy) -> String {
+    let contents = policy.filter_ips(strip_bom(&contents));
+
+    let contents = filter_urls(&contents, policy.url_filtering);
+
+    let mut parser = GameLogParser::new(policy);
+
+    // Censoring rarely makes the output any longer than the input
+    let mut output = String::with_capacity(contents.len());
+    for line in contents.lines() {
+        output.push_str(&parser.parse_line(line, &mut |_, _| {}).into_text());
+        output.push('\n');
+    }
+
+    output
+}
+
+// Reasons for censoring a line because it couldn't be parsed, rather than for privacy
+const UNPARSEABLE_REASONS: &[&str] = &[
+    "no_ts_start",
+    "no_ts_regex_match",
+    "no_category_colon",
+    "no_space_after_timestamp",
+    "game_compat_no_followup",
+    "malformed_login",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedLine {
+    // Starting from 1
+    pub line_number: usize,
+    pub reason: &'static str,
+    // As the parser saw it, so with IPs and URLs already filtered
+    pub line: String,
+}
+
+// Like process_game_log_with_policy, but also returns every line that was censored for not being parseable,
+// for checking whether the rules are too strict. Lines censored for privacy aren't included.
+pub fn process_game_log_with_rejected(
+    contents: &str,
+    policy: &GameLogPolicy,
+) -> (String, Vec<RejectedLine>) {
+    let contents = policy.filter_ips(strip_bom(contents));
+    let contents = filter_urls(&contents, policy.url_filtering);
+
+    let mut parser = GameLogParser::new(policy);
+    let mut output = String::with_capacity(contents.len());
+    let mut rejected = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let parsed = parser.parse_line(line, &mut |_, _| {});
+
+        if let ParsedLine::Censored { reason, .. } = parsed {
+            if UNPARSEABLE_REASONS.contains(&reason) {
+                rejected.push(RejectedLine {
+                    line_number: index + 1,
+                    reason,
+                    line: line.to_owned(),
+                });
+            }
+        }
+
+        output.push_str(&parsed.into_text());
+        output.push('\n');
+    }
+
+    (output, rejected)
+}
+
+// How many lines were censored for each reason, whether the whole line or only part of it, for watching
+// for a spike in something like no_ts_regex_match that means the log format changed upstream.
+// A line censored for more than one reason counts once for each. IPs and URLs are filtered before
+// parsing, so they aren't counted.
+pub fn censor_summary(contents: &str, policy: &GameLogPolicy) -> HashMap<&'static str, usize> {
+    let contents = policy.filter_ips(strip_bom(contents));
+    let contents = filter_urls(&contents, policy.url_filtering);
+
+    let mut parser = GameLogParser::new(policy);
+    let mut summary = HashMap::new();
+    let mut line_reasons = Vec::new();
+
+    for line in contents.lines() {
+        parser.parse_line(line, &mut |reason, _| {
+            if !line_reasons.contains(&reason) {
+                line_reasons.push(reason);
+            }
+        });
+
+        for reason in line_reasons.drain(..) {
+            *summary.entry(reason).or_insert(0) += 1;
+        }
+    }
+
+    summary
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CensoredLine {
+    // Starting from 1
+    pub line_number: usize,
+    // Every reason anything in the line was censored, in the order they happened.
+    // "ip" and "url" for those, since they aren't censored by the parser.
+    pub reasons: Vec<&'static str>,
+    pub original: String,
+    pub sanitized: String,
+}
+
+// For eyeballing what a policy removes, only the lines that had something censored, rather than the whole file.
+// Lines that were only tidied up, like having their whitespace trimmed, aren't included.
+// Goes line by line like process_game_log_audited, so it's slower than process_game_log_with_policy.
+pub fn process_game_log_censored_only(contents: &str, policy: &GameLogPolicy) -> Vec<CensoredLine> {
+    let contents = strip_bom(contents);
+
+    // The parser borrows every line it's given, so they have to outlive it
+    let filtered_lines = contents
         .lines()
-        .map(parse_line)
-        .fold(String::new(), |a, b| a + &b + "\n")
+        .map(|line| {
+            let mut reasons = Vec::new();
+
+            let ip_filtered = policy.filter_ips(line);
+            if matches!(ip_filtered, Cow::Owned(_)) {
+                reasons.push("ip");
+            }
+
+            let filtered = filter_urls(&ip_filtered, policy.url_filtering).into_owned();
+            if filtered != ip_filtered {
+                reasons.push("url");
+            }
+
+            (filtered, reasons)
+        })
+        .collect::<Vec<_>>();
+
+    let mut parser = GameLogParser::new(policy);
+    let mut censored_lines = Vec::new();
+
+    for (index, (line, (filtered_line, reasons))) in
+        contents.lines().zip(&filtered_lines).enumerate()
+    {
+        let mut reasons = reasons.clone();
+
+        let sanitized = parser
+            .parse_line(filtered_line, &mut |reason, _| {
+                if !reasons.contains(&reason) {
+                    reasons.push(reason);
+                }
+            })
+            .into_text();
+
+        if !reasons.is_empty() {
+            censored_lines.push(CensoredLine {
+                line_number: index + 1,
+                reasons,
+                original: line.to_owned(),
+                sanitized: sanitized.into_owned(),
+            });
+        }
+    }
+
+    censored_lines
+}
+
+// For looking into a specific time window. from and to are inclusive, and need to be in the same format
+// as the log's timestamps, but can leave off the end, like "2024-01-02 03:04" for that whole minute.
+// Lines without a timestamp are only kept if the lines on both sides of them are.
+pub fn process_game_log_range(contents: &str, from: &str, to: &str) -> String {
+    process_game_log_range_with_policy(contents, from, to, &GameLogPolicy::default())
+}
+
+pub fn process_game_log_range_with_policy(
+    contents: &str,
+    from: &str,
+    to: &str,
+    policy: &GameLogPolicy,
+) -> String {
+    let contents = policy.filter_ips(strip_bom(contents));
+    let contents = filter_urls(&contents, policy.url_filtering);
+
+    let in_range =
+        |timestamp: &str| timestamp >= from && timestamp.get(..to.len()).unwrap_or(timestamp) <= to;
+
+    let mut parser = GameLogParser::new(policy);
+    let mut output = String::new();
+
+    // Lines without a timestamp since the last line that had one, if that line was kept
+    let mut previous_in_range = false;
+    let mut pending = String::new();
+
+    // Every line still goes through the parser, so that continuation lines are handled the same
+    for line in contents.lines() {
+        let sanitized = parser.parse_line(line, &mut |_, _| {}).into_text();
+
+        match line_timestamp(line) {
+            Some(timestamp) => {
+                let line_in_range = in_range(timestamp);
+                if line_in_range {
+                    if previous_in_range {
+                        output.push_str(&pending);
+                    }
+
+                    output.push_str(&sanitized);
+                    output.push('\n');
+                }
+
+                pending.clear();
+                previous_in_range = line_in_range;
+            }
+
+            None => {
+                if previous_in_range {
+                    pending.push_str(&sanitized);
+                    pending.push('\n');
+                }
+            }
+        }
+    }
+
+    output
+}
+
+// Like process_game_log_with_policy, but reports everything censored to the sink.
+// Goes line by line so that records have line numbers, which is slower.
+pub fn process_game_log_audited(
+    contents: &str,
+    policy: &GameLogPolicy,
+    file: &str,
+    sink: &mut dyn AuditSink,
+) -> String {
+    let contents = strip_bom(contents);
+    let filtered_lines = contents
+        .lines()
+        .map(|line| {
+            let line = policy.filter_ips(line);
+            filter_urls(&line, policy.url_filtering).into_owned()
+        })
+        .collect::<Vec<_>>();
+
+    let mut parser = GameLogParser::new(policy);
+    let mut output = String::with_capacity(contents.len());
+
+    for (index, (line, filtered_line)) in contents.lines().zip(&filtered_lines).enumerate() {
+        let mut record = |reason: &str, original: &str| {
+            sink.record(AuditRecord {
+                file,
+                line: index + 1,
+                reason,
+                original,
+            })
+        };
+
+        for ip in find_ips(line) {
+            record("ip", ip);
+        }
+
+        // The parser only sees the line after IPs were filtered, so the ip/cid it censors is looked up in
+        // the original line, to record the IP with it
+        let mut on_censor = |reason: &str, original: &str| match reason {
+            "ip/cid" => record(reason, unfiltered_word(line, filtered_line, original)),
+            _ => record(reason, original),
+        };
+
+        output.push_str(&parser.parse_line(filtered_line, &mut on_censor).into_text());
+        output.push('\n');
+    }
+
+    output
+}
+
+// The word in line that's at the same place as word is in filtered_line, which word is a slice of.
+// Filtering IPs and URLs never adds or removes spaces, so the words line up.
+fn unfiltered_word<'a>(line: &'a str, filtered_line: &str, word: &'a str) -> &'a str {
+    (word.as_ptr() as usize)
+        .checked_sub(filtered_line.as_ptr() as usize)
+        .and_then(|word_start| filtered_line.get(..word_start))
+        .and_then(|before| line.split(' ').nth(before.matches(' ').count()))
+        .unwrap_or(word)
+}
+
+// For running already published game logs through newer rules.
+// Lines that were censored before would otherwise be censored again as no_ts_start, losing the original reason,
+// so they're left as they are. Everything else about parsing a kept line is idempotent.
+pub fn resanitize_game_log_with_policy(contents: &str, policy: &GameLogPolicy) -> String {
+    static CENSORED_LINE_REGEX: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^-censored\([^)]*\)-$").unwrap());
+
+    let contents = policy.filter_ips(strip_bom(contents));
+    let contents = filter_urls(&contents, policy.url_filtering);
+
+    let mut parser = GameLogParser::new(policy);
+    let mut output = String::with_capacity(contents.len());
+
+    for line in contents.lines() {
+        if CENSORED_LINE_REGEX.is_match(line) {
+            output.push_str(line);
+        } else {
+            output.push_str(&parser.parse_line(line, &mut |_, _| {}).into_text());
+        }
+
+        output.push('\n');
+    }
+
+    output
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineDifference {
+    // Starting from 1
+    pub line_number: usize,
+    pub old: String,
+    pub new: String,
+}
+
+// For reviewing a rules change before rolling it out, returns every line that
+// sanitizes differently under the new policy.
+pub fn diff_game_log_policies(
+    contents: &str,
+    old_policy: &GameLogPolicy,
+    new_policy: &GameLogPolicy,
+) -> Vec<LineDifference> {
+    let old_output = process_game_log_with_policy(contents.to_owned(), old_policy);
+    let new_output = process_game_log_with_policy(contents.to_owned(), new_policy);
+
+    // Neither policy can change how many lines there are
+    old_output
+        .lines()
+        .zip(new_output.lines())
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(index, (old, new))| LineDifference {
+            line_number: index + 1,
+            old: old.to_owned(),
+            new: new.to_owned(),
+        })
+        .collect()
+}
+
+// One JSON object per line, either {timestamp, type, message} or {censored: reason}
+pub fn process_game_log_jsonl(contents: &str) -> String {
+    process_game_log_jsonl_with_policy(contents, &GameLogPolicy::default())
+}
+
+pub fn process_game_log_jsonl_with_policy(contents: &str, policy: &GameLogPolicy) -> String {
+    let contents = policy.filter_ips(strip_bom(contents));
+
+    let mut parser = GameLogParser::new(policy);
+    let mut output = String::new();
+
+    for line in filter_urls(&contents, policy.url_filtering).lines() {
+        let json = match parser.parse_line(line, &mut |_, _| {}) {
+            ParsedLine::Kept {
+                timestamp,
+                log_type,
+                message,
+                ..
+            } => serde_json::json!({
+                "timestamp": timestamp,
+                "type": log_type,
+                "message": message,
+            }),
+
+            ParsedLine::Censored { reason, .. } => serde_json::json!({
+                "censored": reason,
+            }),
+        };
+
+        output.push_str(&json.to_string());
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_too_long() {
+        let line = format!(
+            "[2024-01-02 03:04:05.678] GAME: {}",
+            "a".repeat(8 * 1024 * 1024)
+        );
+
+        let start = std::time::Instant::now();
+        assert_eq!(parse_line(&line), censor!("line_too_long"));
+        assert_eq!(
+            process_game_log(line.clone()),
+            concat!(censor!("line_too_long"), "\n")
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        let policy = GameLogPolicy {
+            max_line_length: None,
+            ..Default::default()
+        };
+        assert_eq!(parse_line_with_policy(&line, &policy), line);
+    }
+
+    #[test]
+    fn test_timestamp_formats() {
+        for line in [
+            "[2024-01-02 03:04:05.678] GAME: with milliseconds",
+            "[2024-01-02 03:04:05] GAME: without milliseconds",
+            "[03:04:05] GAME: time only",
+        ] {
+            assert_eq!(parse_line(line), line);
+        }
+
+        for line in [
+            "[2024-01-02 03:04:05.] GAME: dangling dot",
+            "[2024-01-02 03:04:05.1234] GAME: too many digits",
+            "[2024-01-02] GAME: date only",
+        ] {
+            assert_eq!(parse_line(line), censor!("no_ts_regex_match"), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_strict_game_log_policy() {
+        let policy = GameLogPolicy::strict();
+
+        for (line, expected) in [
+            (
+                "[2024-01-02 03:04:05.678] ACCESS: Logout: Somebody/(Some Body)",
+                "[2024-01-02 03:04:05.678] ACCESS: Logout: Somebody/(Some Body)",
+            ),
+            (
+                "[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"hello\"",
+                censor!("unknown_category"),
+            ),
+            (
+                "[2024-01-02 03:04:05] ACCESS: Logout: Somebody/(Some Body)",
+                censor!("no_ts_regex_match"),
+            ),
+            (
+                "[03:04:05] ACCESS: Logout: Somebody/(Some Body)",
+                censor!("no_ts_regex_match"),
+            ),
+        ] {
+            assert_eq!(parse_line_with_policy(line, &policy), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_game_compat_wrapped_types() {
+        for (line, expected) in [
+            (
+                "[2024-01-02 03:04:05.678] GAME-COMPAT: ADMINPRIVATE: Something private",
+                censor!("private logtype"),
+            ),
+            (
+                "[2024-01-02 03:04:05.678] GAME-COMPAT: GAME-ADMINPRIVATE: Something private",
+                censor!("private logtype"),
+            ),
+            (
+                "[2024-01-02 03:04:05.678] GAME-COMPAT: TOPIC: \"1.2.3.4\" (\"/?ping\")",
+                censor!("world_topic logs"),
+            ),
+            (
+                "[2024-01-02 03:04:05.678] GAME-COMPAT: SQL: Query failed",
+                censor!("sql logs"),
+            ),
+            // Without a colon, it would otherwise be matched as "ADMINPRIVAT"
+            (
+                "[2024-01-02 03:04:05.678] GAME-COMPAT: ADMINPRIVATE Something private",
+                censor!("no_category_colon"),
+            ),
+            (
+                "[2024-01-02 03:04:05.678] GAME-COMPAT: SAY: Somebody/(Some Body) \"hello\"",
+                "[2024-01-02 03:04:05.678] GAME-COMPAT: SAY: Somebody/(Some Body) \"hello\"",
+            ),
+        ] {
+            assert_eq!(parse_line(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_empty_game_compat() {
+        for line in [
+            "[2024-01-02 03:04:05.678] GAME-COMPAT:",
+            "[2024-01-02 03:04:05.678] GAME-COMPAT: ",
+        ] {
+            assert_eq!(
+                parse_line(line),
+                censor!("game_compat_no_followup"),
+                "{line:?}"
+            );
+
+            let policy = GameLogPolicy {
+                allow_empty_game_compat: true,
+                ..Default::default()
+            };
+            assert_eq!(
+                parse_line_with_policy(line, &policy),
+                "[2024-01-02 03:04:05.678] GAME-COMPAT:",
+                "{line:?}"
+            );
+            assert_eq!(
+                parse_line_structured(line, &policy),
+                ParsedLine::Kept {
+                    timestamp: "2024-01-02 03:04:05.678",
+                    log_type: Some("GAME-COMPAT"),
+                    message: Cow::Borrowed(""),
+                    line: Cow::Borrowed("[2024-01-02 03:04:05.678] GAME-COMPAT:"),
+                }
+            );
+
+            let mut strict = policy;
+            strict.apply_strict_mode();
+            assert_eq!(
+                parse_line_with_policy(line, &strict),
+                censor!("game_compat_no_followup")
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_policy() {
+        let line = "[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"hello\"";
+        let access = "[2024-01-02 03:04:05.678] ACCESS: Logout: Somebody/(Some Body)";
+
+        let allow = GameLogPolicy {
+            unknown_policy: UnknownPolicy::Allow,
+            ..Default::default()
+        };
+        assert_eq!(parse_line_with_policy(line, &allow), line);
+        assert_eq!(parse_line_with_policy(access, &allow), access);
+
+        let censor = GameLogPolicy {
+            unknown_policy: UnknownPolicy::Censor,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_line_with_policy(line, &censor),
+            censor!("unknown_category")
+        );
+        assert_eq!(parse_line_with_policy(access, &censor), access);
+    }
+
+    #[test]
+    fn test_continuation_lines() {
+        let contents = "\
+continuing from nothing
+[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"first line
+  second line
+third line\"
+
+and after a blank line
+[2024-01-02 03:04:06.000] ADMINPRIVATE: Something private
+  that goes on
+[2024-01-02 03:04:07.000] GAME-SAY: Somebody/(Some Body) \"hello\"
+";
+
+        assert_eq!(
+            process_game_log(contents.to_owned()),
+            concat!(
+                censor!("no_ts_start"),
+                "\n",
+                "[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"first line\n",
+                censor!("no_ts_start"),
+                "\n",
+                censor!("no_ts_start"),
+                "\n",
+                censor!("empty_line"),
+                "\n",
+                censor!("no_ts_start"),
+                "\n",
+                censor!("private logtype"),
+                "\n",
+                censor!("no_ts_start"),
+                "\n",
+                "[2024-01-02 03:04:07.000] GAME-SAY: Somebody/(Some Body) \"hello\"\n",
+            )
+        );
+
+        let policy = GameLogPolicy {
+            continuation_lines: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            process_game_log_with_policy(contents.to_owned(), &policy),
+            concat!(
+                censor!("no_ts_start"),
+                "\n",
+                "[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"first line\n",
+                "  second line\n",
+                "third line\"\n",
+                censor!("empty_line"),
+                "\n",
+                "and after a blank line\n",
+                censor!("private logtype"),
+                "\n",
+                censor!("private logtype"),
+                "\n",
+                "[2024-01-02 03:04:07.000] GAME-SAY: Somebody/(Some Body) \"hello\"\n",
+            )
+        );
+
+        let jsonl = process_game_log_jsonl_with_policy(contents, &policy);
+        let second_line =
+            serde_json::from_str::<serde_json::Value>(jsonl.lines().nth(2).unwrap()).unwrap();
+        assert_eq!(
+            second_line,
+            serde_json::json!({
+                "timestamp": "2024-01-02 03:04:05.678",
+                "type": "GAME-SAY",
+                "message": "second line",
+            })
+        );
+    }
+
+    #[test]
+    fn test_continuation_lines_too_long() {
+        let contents = format!(
+            "[2024-01-02 03:04:05.678] GAME: first line\n{}\nlast line\n",
+            "a".repeat(100)
+        );
+
+        let policy = GameLogPolicy {
+            continuation_lines: true,
+            max_line_length: Some(64),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            process_game_log_with_policy(contents, &policy),
+            concat!(
+                "[2024-01-02 03:04:05.678] GAME: first line\n",
+                censor!("line_too_long"),
+                "\n",
+                "last line\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_continuation_lines_embedded_json() {
+        let contents = r#"[2024-01-02 03:04:05.678] GAME: Vote result:
+{"ckey":"somebody","address":"somewhere","choice":"yes"}
+"#;
+
+        let policy = GameLogPolicy {
+            continuation_lines: true,
+            embedded_json_keys: vec!["ckey".to_owned(), "address".to_owned()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            process_game_log_with_policy(contents.to_owned(), &policy),
+            r#"[2024-01-02 03:04:05.678] GAME: Vote result:
+{"address":"-censored(embedded json)-","choice":"yes","ckey":"-censored(embedded json)-"}
+"#
+        );
+    }
+
+    #[test]
+    fn test_filter_account_ids() {
+        let line = "[2024-01-02 03:04:05.678] ACCESS: Steam link: Somebody/(Some Body) account=76561198000000000 ID=12345";
+
+        assert_eq!(
+            parse_line(line),
+            "[2024-01-02 03:04:05.678] ACCESS: Steam link: Somebody/(Some Body) account=-censored(account id)- ID=-censored(account id)-"
+        );
+
+        let policy = GameLogPolicy {
+            filter_account_ids: false,
+            ..Default::default()
+        };
+        assert_eq!(parse_line_with_policy(line, &policy), line);
+
+        for line in [
+            "[2024-01-02 03:04:05.678] GAME: Somebody/(Some Body) bought 12345 credits",
+            "[2024-01-02 03:04:05.678] GAME: Somebody/(Some Body) set UID=12345 and id=5",
+            "[2024-01-02 03:04:05.678] GAME: Somebody/(Some Body) set account=abc",
+        ] {
+            assert_eq!(parse_line(line), line);
+        }
+    }
+
+    #[test]
+    fn test_adminprivate_allowlist() {
+        let allowed =
+            "[2024-01-02 03:04:05.678] ADMINPRIVATE: ADMINTOOL: Someone opened the player panel";
+        let wrapped = "[2024-01-02 03:04:05.678] GAME-COMPAT: ADMINPRIVATE: ADMINTOOL: Someone opened the player panel";
+        let private = "[2024-01-02 03:04:05.678] ADMINPRIVATE: NOTE: Someone added a note";
+
+        for line in [allowed, wrapped, private] {
+            assert_eq!(parse_line(line), censor!("private logtype"), "{line}");
+        }
+
+        let policy = GameLogPolicy {
+            adminprivate_allowlist: vec!["ADMINTOOL:".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(parse_line_with_policy(allowed, &policy), allowed);
+        assert_eq!(parse_line_with_policy(wrapped, &policy), wrapped);
+        assert_eq!(
+            parse_line_with_policy(private, &policy),
+            censor!("private logtype")
+        );
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        assert_eq!(
+            process_game_log(
+                "\u{feff}[2024-01-02 03:04:05.678] Starting up round ID 123.\n".to_owned()
+            ),
+            "[2024-01-02 03:04:05.678] Starting up round ID 123.\n"
+        );
+    }
+
+    #[test]
+    fn test_embedded_json() {
+        let line = r#"[2024-01-02 03:04:05.678] GAME: Vote result: {"ckey":"somebody","votes":[{"ckey":"else","choice":"yes"}],"address":"1.2.3.4"}"#;
+
+        // Off by default, though the IP is still caught when filtering the whole log
+        assert_eq!(parse_line(line), line);
+
+        let policy = GameLogPolicy {
+            embedded_json_keys: vec!["ckey".to_owned(), "address".to_owned()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse_line_with_policy(line, &policy),
+            r#"[2024-01-02 03:04:05.678] GAME: Vote result: {"address":"-censored(embedded json)-","ckey":"-censored(embedded json)-","votes":[{"choice":"yes","ckey":"-censored(embedded json)-"}]}"#
+        );
+
+        // Only a whole object at the end of the line
+        for line in [
+            r#"[2024-01-02 03:04:05.678] GAME: {"ckey":"somebody"} said hello"#,
+            r#"[2024-01-02 03:04:05.678] GAME: not json {"ckey": somebody}"#,
+            r#"[2024-01-02 03:04:05.678] GAME: nothing to censor {"votes":3}"#,
+        ] {
+            assert_eq!(parse_line_with_policy(line, &policy), line);
+        }
+    }
+
+    #[test]
+    fn test_process_game_log_with_rejected() {
+        let contents = "\
+[2024-01-02 03:04:05.000] GAME: fine
+[yesterday] GAME: bad timestamp from 1.2.3.4
+[2024-01-02 03:04:06.000] no colon
+[2024-01-02 03:04:07.000] ADMINPRIVATE: private
+";
+
+        let (output, rejected) =
+            process_game_log_with_rejected(contents, &GameLogPolicy::default());
+
+        assert_eq!(
+            output,
+            process_game_log(contents.to_owned()),
+            "output should be the same as without collecting rejected lines"
+        );
+
+        assert_eq!(
+            rejected,
+            vec![
+                RejectedLine {
+                    line_number: 2,
+                    reason: "no_ts_regex_match",
+                    line: "[yesterday] GAME: bad timestamp from -censored-".to_owned(),
+                },
+                RejectedLine {
+                    line_number: 3,
+                    reason: "no_category_colon",
+                    line: "[2024-01-02 03:04:06.000] no colon".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_censor_summary() {
+        let contents = "\
+[2024-01-02 03:04:05.000] GAME: fine
+[yesterday] GAME: bad timestamp
+[tomorrow] GAME: bad timestamp
+[2024-01-02 03:04:06.000] no colon
+[2024-01-02 03:04:07.000] ADMINPRIVATE: private
+[2024-01-02 03:04:08.000] GAME: logged in with account=123 and ID=456
+";
+
+        assert_eq!(
+            censor_summary(contents, &GameLogPolicy::default()),
+            HashMap::from([
+                ("no_ts_regex_match", 2),
+                ("no_category_colon", 1),
+                ("private logtype", 1),
+                ("account id", 1),
+            ])
+        );
+
+        assert!(censor_summary("", &GameLogPolicy::default()).is_empty());
+    }
+
+    #[test]
+    fn test_process_game_log_censored_only() {
+        let contents = "\
+[2024-01-02 03:04:05.000] GAME: fine
+[2024-01-02 03:04:06.000] ACCESS: Login: somebody/(Some Body) from 1.2.3.4 || BYOND v515
+[yesterday] GAME: bad timestamp
+[2024-01-02 03:04:07.000] GAME: logged in with account=123
+[2024-01-02 03:04:08.000] GAME: also fine
+";
+
+        let censored_lines = process_game_log_censored_only(contents, &GameLogPolicy::default());
+
+        assert_eq!(
+            censored_lines
+                .iter()
+                .map(|censored| (censored.line_number, censored.reasons.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, vec!["ip", "ip/cid"]),
+                (3, vec!["no_ts_regex_match"]),
+                (4, vec!["account id"]),
+            ]
+        );
+
+        // The same lines as the whole file sanitized
+        let sanitized = process_game_log(contents.to_owned());
+        let sanitized_lines = sanitized.lines().collect::<Vec<_>>();
+        for censored in &censored_lines {
+            assert_eq!(
+                censored.original,
+                contents.lines().nth(censored.line_number - 1).unwrap()
+            );
+            assert_eq!(
+                censored.sanitized,
+                sanitized_lines[censored.line_number - 1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_game_log_censored_only_ignores_whitespace() {
+        let contents = "[2024-01-02 03:04:05.000] GAME: fine   \r\n  [2024-01-02 03:04:06.000] GAME: also fine\t\n";
+
+        assert_eq!(
+            process_game_log_censored_only(contents, &GameLogPolicy::default()),
+            []
+        );
+    }
+
+    #[test]
+    fn test_process_game_log_range() {
+        let contents = "\
+[2024-01-02 03:04:04.999] GAME: before
+[2024-01-02 03:04:05.000] GAME: first from 1.2.3.4
+no timestamp between
+[2024-01-02 03:04:06.500] GAME: middle
+[2024-01-02 03:04:07.999] GAME: last
+no timestamp after
+[2024-01-02 03:04:08.000] GAME: after
+";
+
+        assert_eq!(
+            process_game_log_range(contents, "2024-01-02 03:04:05", "2024-01-02 03:04:07"),
+            "\
+[2024-01-02 03:04:05.000] GAME: first from -censored-
+-censored(no_ts_start)-
+[2024-01-02 03:04:06.500] GAME: middle
+[2024-01-02 03:04:07.999] GAME: last
+"
+        );
+
+        assert_eq!(
+            process_game_log_range(
+                contents,
+                "2024-01-02 03:04:05.000",
+                "2024-01-02 03:04:05.000"
+            ),
+            "[2024-01-02 03:04:05.000] GAME: first from -censored-\n"
+        );
+
+        let policy = GameLogPolicy {
+            continuation_lines: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            process_game_log_range_with_policy(
+                contents,
+                "2024-01-02 03:04:06",
+                "2024-01-02 03:04:08.000",
+                &policy
+            ),
+            "\
+[2024-01-02 03:04:06.500] GAME: middle
+[2024-01-02 03:04:07.999] GAME: last
+no timestamp after
+[2024-01-02 03:04:08.000] GAME: after
+"
+        );
+
+        assert_eq!(
+            process_game_log_range(contents, "2024-01-03", "2024-01-04"),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_duplicate_timestamps() {
+        let line = "[2024-01-02 03:04:05.678] GAME: Somebody did something";
+        assert_eq!(
+            parse_line(
+                "[2024-01-02 03:04:05.678][2024-01-02 03:04:05.678] GAME: Somebody did something"
+            ),
+            line
+        );
+        assert_eq!(
+            parse_line(
+                "[03:04:05][03:04:05][2024-01-02 03:04:05.678] GAME: Somebody did something"
+            ),
+            line
+        );
+
+        // Still censored like any other line once it's down to one timestamp
+        assert_eq!(
+            parse_line("[2024-01-02 03:04:05.678][2024-01-02 03:04:05.678] ACCESS: Login: Somebody from 1.2.3.4-1234567890 || BYOND v515"),
+            "[2024-01-02 03:04:05.678] ACCESS: Login: Somebody from -censored(ip/cid)- || BYOND v515"
+        );
+
+        assert_eq!(
+            parse_line("[03:04:05] [03:04:05] GAME: Somebody did something"),
+            "[03:04:05] GAME: Somebody did something"
+        );
+
+        // Whitespace between them that isn't one byte long
+        assert_eq!(
+            parse_line("[03:04:05]\u{3000}\u{3000}\u{3000}\u{3000}[03:04:05] GAME: x"),
+            "[03:04:05] GAME: x"
+        );
+
+        // Only timestamps are stripped
+        assert_eq!(
+            parse_line("[2024-01-02 03:04:05.678][not a timestamp] GAME: Somebody did something"),
+            censor!("no_space_after_timestamp")
+        );
+    }
+
+    #[test]
+    fn test_ip_free_log_types() {
+        let contents = "\
+[2024-01-02 03:04:05.678] GAME-ATTACK: Somebody/(Some Body) has punched Someone/(Some One) at 1.2.3.4
+[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"my ip is 1.2.3.4\"
+[2024-01-02 03:04:05.678] ACCESS: Login: Somebody/(Some Body) from 1.2.3.4-1234567890 || BYOND v515
+[2024-01-02 03:04:05.678][2024-01-02 03:04:05.678] GAME-ATTACK: 5.6.7.8
+[2024-01-02 03:04:05.678] GAME-COMPAT: ATTACK: 5.6.7.8
+";
+
+        let ip_free = GameLogPolicy {
+            ip_free_log_types: vec!["ATTACK".to_owned(), "ACCESS".to_owned()],
+            ..Default::default()
+        };
+
+        let default_output = process_game_log(contents.to_owned());
+        let ip_free_output = process_game_log_with_policy(contents.to_owned(), &ip_free);
+
+        // The same as without it, whether or not a line has an IP
+        assert_eq!(ip_free_output, default_output);
+        assert!(!crate::parsers::ip_filtering::contains_ip(&ip_free_output));
+
+        let no_ips = "[2024-01-02 03:04:05.678] GAME-ATTACK: Somebody/(Some Body) has punched Someone/(Some One) (Bridge (1,2,3))\n";
+        assert_eq!(
+            process_game_log_with_policy(no_ips.to_owned(), &ip_free),
+            process_game_log(no_ips.to_owned())
+        );
+
+        // Every other entry point too
+        let default_policy = GameLogPolicy::default();
+        assert_eq!(
+            process_game_log_with_rejected(contents, &ip_free),
+            process_game_log_with_rejected(contents, &default_policy)
+        );
+        assert_eq!(
+            censor_summary(contents, &ip_free),
+            censor_summary(contents, &default_policy)
+        );
+        assert_eq!(
+            process_game_log_censored_only(contents, &ip_free),
+            process_game_log_censored_only(contents, &default_policy)
+        );
+        assert_eq!(
+            process_game_log_jsonl_with_policy(contents, &ip_free),
+            process_game_log_jsonl(contents)
+        );
+
+        let labeled = GameLogPolicy {
+            label_ip_censors: true,
+            ..ip_free.clone()
+        };
+        assert!(process_game_log_with_policy(contents.to_owned(), &labeled)
+            .contains("at -censored(ip:game)-"));
+    }
+
+    #[test]
+    fn test_may_contain_ip() {
+        assert!(may_contain_ip("at 1.2.3.4"));
+        assert!(may_contain_ip("version 515.1630"));
+        assert!(!may_contain_ip("(Bridge (123,45,2)) has punched Someone."));
+    }
+
+    #[test]
+    fn test_short_login() {
+        for line in [
+            "[2024-01-02 03:04:05.678] ACCESS: Login:",
+            "[2024-01-02 03:04:05.678] ACCESS: Login: Somebody from",
+        ] {
+            assert_eq!(parse_line(line), censor!("malformed_login"), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_failed_access_keeps_timestamp() {
+        assert_eq!(
+            parse_line("[2024-01-02 03:04:08.000] ACCESS: Failed Login: Somebody 10.20.30.40 1234567890 - ban"),
+            "[2024-01-02 03:04:08.000] ACCESS: Failed connection -censored(invalid connection data)-"
+        );
+    }
+
+    #[test]
+    fn test_safe_log_types() {
+        let admin = "[2024-01-02 03:04:05.678] ADMIN: HELP: Somebody/(Some Body) account=123";
+        let wrapped = "[2024-01-02 03:04:05.678] GAME-COMPAT: ADMIN: PM: something";
+        let bad_timestamp = "[yesterday] ADMIN: HELP: something";
+
+        let policy = GameLogPolicy {
+            safe_log_types: vec!["ADMIN".to_owned()],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            parse_line_with_policy(admin, &policy),
+            Cow::Borrowed(line) if line == admin
+        ));
+        assert_eq!(parse_line_with_policy(wrapped, &policy), wrapped);
+        assert_eq!(
+            parse_line_with_policy(bad_timestamp, &policy),
+            censor!("no_ts_regex_match")
+        );
+
+        assert_eq!(
+            parse_line_with_policy(admin, &GameLogPolicy::strict()),
+            censor!("asay/apm/ahelp/notes/etc")
+        );
+    }
+
+    // Each input covers a group of censor reasons or recognized categories.
+    // After changing the rules, review the differences with `cargo insta review`.
+    #[test]
+    fn test_game_log_snapshots() {
+        let inputs_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/game-snapshots");
+        insta::glob!(inputs_path, "*.game.log", |path| {
+            let contents = std::fs::read_to_string(path).unwrap();
+            insta::assert_snapshot!(process_game_log(contents));
+        });
+    }
+
+    #[test]
+    fn test_filter_hostnames() {
+        let line = "[2024-01-02 03:04:05.678] ACCESS: Login: Somebody/(Some Body) from (host-12-34.isp.example.com) 1.2.3.4-1234567890 || BYOND v515.1630";
+
+        assert_eq!(
+            parse_line(line),
+            "[2024-01-02 03:04:05.678] ACCESS: Login: Somebody/(Some Body) from -censored(hostname)- -censored(ip/cid)- || BYOND v515.1630"
+        );
+
+        let policy = GameLogPolicy {
+            filter_hostnames: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_line_with_policy(line, &policy),
+            "[2024-01-02 03:04:05.678] ACCESS: Login: Somebody/(Some Body) from (host-12-34.isp.example.com) -censored(ip/cid)- || BYOND v515.1630"
+        );
+
+        for word in [
+            "v515.1630",
+            "thing.dm",
+            "Some.Body.Name",
+            "/obj/item.toolbox",
+            "1.2.3.4",
+            "tgstation13.org",
+        ] {
+            assert!(!is_hostname(word), "{word}");
+        }
+    }
+
+    #[test]
+    fn test_process_game_log_audited() {
+        let contents = "\
+[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"my ip is 1.2.3.4\"
+[2024-01-02 03:04:06.000] ACCESS: Login: Somebody/(Some Body) from (host-12-34.isp.example.com) 5.6.7.8-1234567890 || BYOND v515.1630
+[2024-01-02 03:04:07.000] ADMINPRIVATE: Something private happened
+[2024-01-02 03:04:08.000] GAME-SAY: Somebody/(Some Body) \"hello\"
+";
+
+        let mut records = Vec::new();
+        let output = process_game_log_audited(
+            contents,
+            &GameLogPolicy::default(),
+            "round-1/game.log",
+            &mut |record: AuditRecord| {
+                records.push((
+                    record.line,
+                    record.reason.to_owned(),
+                    record.original.to_owned(),
+                ))
+            },
+        );
+
+        assert_eq!(output, process_game_log(contents.to_owned()));
+        assert_eq!(
+            records,
+            [
+                (1, "ip".to_owned(), "1.2.3.4".to_owned()),
+                (2, "ip".to_owned(), "5.6.7.8".to_owned()),
+                (2, "ip/cid".to_owned(), "5.6.7.8-1234567890".to_owned()),
+                (
+                    2,
+                    "hostname".to_owned(),
+                    "(host-12-34.isp.example.com)".to_owned()
+                ),
+                (
+                    3,
+                    "private logtype".to_owned(),
+                    "[2024-01-02 03:04:07.000] ADMINPRIVATE: Something private happened".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_game_log_policies() {
+        let contents = "\
+[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"hello\"
+[2024-01-02 03:04:06.000] ACCESS: Logout: Somebody/(Some Body)
+[2024-01-02 03:04:07.000] GAME-OOC: Somebody/(Some Body) \"https://example.com/?token=abc\"
+";
+
+        let old_policy = GameLogPolicy::default();
+        let new_policy = GameLogPolicy {
+            unknown_policy: UnknownPolicy::Censor,
+            url_filtering: UrlFiltering::StripQuery,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            diff_game_log_policies(contents, &old_policy, &old_policy),
+            []
+        );
+
+        assert_eq!(
+            diff_game_log_policies(contents, &old_policy, &new_policy),
+            [
+                LineDifference {
+                    line_number: 1,
+                    old: "[2024-01-02 03:04:05.678] GAME-SAY: Somebody/(Some Body) \"hello\"".to_owned(),
+                    new: censor!("unknown_category").to_owned(),
+                },
+                LineDifference {
+                    line_number: 3,
+                    old: "[2024-01-02 03:04:07.000] GAME-OOC: Somebody/(Some Body) \"https://example.com/?token=abc\"".to_owned(),
+                    new: censor!("unknown_category").to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let contents = "\
+[2024-01-02 03:04:05.678] Starting up round ID 123456.
+[2024-01-02 03:04:06.000] ACCESS: Login: Somebody/(Some Body) from 1.2.3.4-1234567890 || BYOND v515.1630
+[2024-01-02 03:04:07.000] GAME-COMPAT: SAY: Somebody/(Some Body) \"hello\"
+[2024-01-02 03:04:08.000] ADMINPRIVATE: Something private happened
+not a log line
+";
+
+        let lines = process_game_log_jsonl(contents)
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            [
+                serde_json::json!({
+                    "timestamp": "2024-01-02 03:04:05.678",
+                    "type": null,
+                    "message": "Starting up round ID 123456.",
+                }),
+                serde_json::json!({
+                    "timestamp": "2024-01-02 03:04:06.000",
+                    "type": "ACCESS",
+                    "message": "Login: Somebody/(Some Body) from -censored(ip/cid)- || BYOND v515.1630",
+                }),
+                serde_json::json!({
+                    "timestamp": "2024-01-02 03:04:07.000",
+                    "type": "SAY",
+                    "message": "Somebody/(Some Body) \"hello\"",
+                }),
+                serde_json::json!({ "censored": "private logtype" }),
+                serde_json::json!({ "censored": "no_ts_start" }),
+            ]
+        );
+    }
 }
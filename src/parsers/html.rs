@@ -0,0 +1,143 @@
+// HTML logs only have their text sanitized, so tags and attributes come out exactly as they went in.
+use std::borrow::Cow;
+
+use super::{
+    base64_filtering::filter_base64_blobs,
+    coordinate_filtering::filter_coordinates,
+    ip_filtering::{filter_ips_in, IpCensorContext},
+    keyword_filtering::{filter_keywords, keyword_regex},
+    PassthroughPolicy,
+};
+
+pub fn process_html_log(contents: String) -> String {
+    process_html_log_with_policy(&contents, "", &PassthroughPolicy::default())
+}
+
+pub fn process_html_log_with_policy(
+    contents: &str,
+    filename: &str,
+    policy: &PassthroughPolicy,
+) -> String {
+    process_html_log_with_text_filter(contents, filename, policy, |text| Cow::Borrowed(text))
+}
+
+// For HTML logs that need more censored than the policy does, text_filter is run on the text last
+pub(crate) fn process_html_log_with_text_filter(
+    contents: &str,
+    filename: &str,
+    policy: &PassthroughPolicy,
+    text_filter: impl for<'a> Fn(&'a str) -> Cow<'a, str>,
+) -> String {
+    let keyword_regex = keyword_regex(&policy.keyword_blocklist);
+    let mask_coordinates = policy.mask_coordinates_in.contains(filename);
+
+    let filter_text = |text: &str, output: &mut String| {
+        let text = filter_ips_in(
+            text,
+            policy.label_ip_censors.then_some(IpCensorContext::Html),
+        );
+        let text = filter_keywords(&text, keyword_regex.as_ref());
+        let text = if mask_coordinates {
+            Cow::Owned(filter_coordinates(&text).into_owned())
+        } else {
+            text
+        };
+        let text = match policy.base64_min_length {
+            Some(min_length) => Cow::Owned(filter_base64_blobs(&text, min_length).into_owned()),
+            None => text,
+        };
+
+        output.push_str(&text_filter(&text));
+    };
+
+    let mut output = String::with_capacity(contents.len());
+    let mut rest = contents;
+
+    while let Some(tag_start) = rest.find('<') {
+        let (text, after) = rest.split_at(tag_start);
+
+        match tag_length(after) {
+            Some(tag_length) => {
+                filter_text(text, &mut output);
+                output.push_str(&after[..tag_length]);
+                rest = &after[tag_length..];
+            }
+
+            // Not a tag, like "a < b", so it's just more text
+            None => {
+                let not_a_tag = tag_start + 1;
+                filter_text(&rest[..not_a_tag], &mut output);
+                rest = &rest[not_a_tag..];
+            }
+        }
+    }
+
+    filter_text(rest, &mut output);
+
+    output
+}
+
+// How long the tag at the start of this is, including the angle brackets.
+// Comments are treated as text, since they aren't attributes and could contain anything.
+fn tag_length(contents: &str) -> Option<usize> {
+    let mut chars = contents.char_indices().skip(1);
+
+    match chars.next() {
+        Some((_, next)) if next.is_ascii_alphabetic() || next == '/' => {}
+        Some((_, '!')) if !contents.starts_with("<!--") => {}
+        _ => return None,
+    }
+
+    let mut quote = None;
+    for (index, char) in chars {
+        match (quote, char) {
+            (None, '"' | '\'') => quote = Some(char),
+            (Some(open), _) if open == char => quote = None,
+            (None, '>') => return Some(index + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_text_is_filtered() {
+        assert_eq!(
+            process_html_log(
+                r#"<a href="?src=1.2.3.4" title='a > b 5.6.7.8'>Somebody from 1.2.3.4</a> and 9.8.7.6<br/>"#
+                    .to_owned()
+            ),
+            r#"<a href="?src=1.2.3.4" title='a > b 5.6.7.8'>Somebody from -censored-</a> and -censored-<br/>"#
+        );
+    }
+
+    #[test]
+    fn test_not_tags() {
+        assert_eq!(
+            process_html_log("1 < 2 from 1.2.3.4 <!-- 5.6.7.8 --> <b".to_owned()),
+            "1 < 2 from -censored- <!-- -censored- --> <b"
+        );
+    }
+
+    #[test]
+    fn test_keyword_blocklist() {
+        let policy = PassthroughPolicy {
+            keyword_blocklist: vec!["secret".to_owned()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            process_html_log_with_policy(
+                r#"<span class="secret">a secret</span>"#,
+                "records.html",
+                &policy
+            ),
+            r#"<span class="secret">a -censored(keyword)-</span>"#
+        );
+    }
+}
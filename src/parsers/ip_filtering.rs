@@ -1,11 +1,325 @@
-use std::{borrow::Cow, sync::LazyLock};
+use std::{borrow::Cow, collections::HashSet, net::Ipv4Addr, sync::LazyLock};
 
-use regex::Regex;
+use regex::{Captures, Regex};
 
+// Octets can be zero padded, like 192.168.001.001, which is still an address
 static IP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?:(?:25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9][0-9]|[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9][0-9]?|[0-9])").unwrap()
+    Regex::new(r"(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9]?[0-9])").unwrap()
 });
 
+// The canonical idea of what an IP is, so anything else looking for them doesn't drift from it
+pub fn ip_regex() -> &'static Regex {
+    &IP_REGEX
+}
+
+pub fn find_ips(contents: &str) -> impl Iterator<Item = &str> {
+    IP_REGEX.find_iter(contents).map(|found| found.as_str())
+}
+
+// Every distinct IP in contents, in the order they first show up. This is for admins looking at the
+// original logs, like to build an allowlist, so never put it anywhere public.
+pub fn collect_ips(contents: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+
+    find_ips(contents)
+        .filter(|ip| seen.insert(*ip))
+        .map(str::to_owned)
+        .collect()
+}
+
+// For checking output is clean without changing it, like in tests or before publishing
+pub fn contains_ip(contents: &str) -> bool {
+    IP_REGEX.is_match(contents)
+}
+
 pub fn filter_ips<'a>(contents: &'a str) -> Cow<'a, str> {
     IP_REGEX.replace_all(contents, "-censored-")
 }
+
+// Like filter_ips, but each IP becomes as many asterisks as it was long, for viewers that line up columns
+pub fn filter_ips_masked<'a>(contents: &'a str) -> Cow<'a, str> {
+    IP_REGEX.replace_all(contents, |captures: &Captures| {
+        "*".repeat(captures[0].len())
+    })
+}
+
+// Like filter_ips, but calls on_censor with every IP it replaces
+pub fn filter_ips_audited<'a>(contents: &'a str, mut on_censor: impl FnMut(&str)) -> Cow<'a, str> {
+    IP_REGEX.replace_all(contents, |captures: &Captures| {
+        on_censor(&captures[0]);
+        "-censored-"
+    })
+}
+
+// Which pipeline censored an IP, for telling them apart when reviewing output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpCensorContext {
+    Game,
+    Runtime,
+    Html,
+    Passthrough,
+}
+
+impl IpCensorContext {
+    pub fn censor_text(self) -> &'static str {
+        match self {
+            IpCensorContext::Game => "-censored(ip:game)-",
+            IpCensorContext::Runtime => "-censored(ip:runtime)-",
+            IpCensorContext::Html => "-censored(ip:html)-",
+            IpCensorContext::Passthrough => "-censored(ip:passthrough)-",
+        }
+    }
+}
+
+// Like filter_ips, but with the context in the censor text if there is one
+pub fn filter_ips_in<'a>(contents: &'a str, context: Option<IpCensorContext>) -> Cow<'a, str> {
+    match context {
+        Some(context) => IP_REGEX.replace_all(contents, context.censor_text()),
+        None => filter_ips(contents),
+    }
+}
+
+// Like filter_ips, for contents that might not be UTF-8 and don't need to be
+pub fn filter_ips_bytes<'a>(contents: &'a [u8]) -> Cow<'a, [u8]> {
+    static IP_BYTES_REGEX: LazyLock<regex::bytes::Regex> =
+        LazyLock::new(|| regex::bytes::Regex::new(IP_REGEX.as_str()).unwrap());
+
+    IP_BYTES_REGEX.replace_all(contents, b"-censored-".as_slice())
+}
+
+// Like filter_ips, but leaves private, loopback, and link-local addresses alone,
+// since those are our own infrastructure rather than players.
+pub fn filter_public_ips<'a>(contents: &'a str) -> Cow<'a, str> {
+    IP_REGEX.replace_all(contents, |captures: &Captures| {
+        let ip = &captures[0];
+        match parse_ipv4(ip) {
+            Some(address) if is_internal(address) => ip.to_owned(),
+            _ => "-censored-".to_owned(),
+        }
+    })
+}
+
+// Like filter_ips, but leaves the allowed addresses alone, like our own monitoring for internal copies.
+// Compared as addresses rather than text, so 192.168.001.001 is the same as 192.168.1.1.
+pub fn filter_ips_except<'a>(contents: &'a str, allow: &HashSet<Ipv4Addr>) -> Cow<'a, str> {
+    if allow.is_empty() {
+        return filter_ips(contents);
+    }
+
+    IP_REGEX.replace_all(contents, |captures: &Captures| {
+        let ip = &captures[0];
+        match parse_ipv4(ip) {
+            Some(address) if allow.contains(&address) => ip.to_owned(),
+            _ => "-censored-".to_owned(),
+        }
+    })
+}
+
+// Ipv4Addr's FromStr refuses leading zeros, which are still the same address to us
+fn parse_ipv4(ip: &str) -> Option<Ipv4Addr> {
+    let mut octets = [0; 4];
+    let mut parts = ip.split('.');
+
+    for octet in &mut octets {
+        let part = parts.next()?;
+        if part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        *octet = part.parse().ok()?;
+    }
+
+    parts.next().is_none().then(|| Ipv4Addr::from(octets))
+}
+
+fn is_internal(address: Ipv4Addr) -> bool {
+    match address.octets() {
+        // 10.0.0.0/8
+        [10, ..] => true,
+        // 172.16.0.0/12
+        [172, 16..=31, ..] => true,
+        // 192.168.0.0/16
+        [192, 168, ..] => true,
+        // 127.0.0.0/8
+        [127, ..] => true,
+        // 169.254.0.0/16
+        [169, 254, ..] => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_ips_in() {
+        let line = "from 1.2.3.4";
+
+        assert_eq!(filter_ips_in(line, None), "from -censored-");
+        assert_eq!(
+            filter_ips_in(line, Some(IpCensorContext::Game)),
+            "from -censored(ip:game)-"
+        );
+        assert_eq!(
+            filter_ips_in(line, Some(IpCensorContext::Passthrough)),
+            "from -censored(ip:passthrough)-"
+        );
+    }
+
+    #[test]
+    fn test_filter_ips_bytes() {
+        let contents = b"\xff\xfe from 1.2.3.4 \x00";
+
+        assert_eq!(
+            filter_ips_bytes(contents).as_ref(),
+            b"\xff\xfe from -censored- \x00"
+        );
+        assert!(matches!(
+            filter_ips_bytes(b"nothing here"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_filter_ips_masked() {
+        let line = "from 1.2.3.4   to 255.255.255.255 | done";
+        let masked = filter_ips_masked(line);
+
+        assert_eq!(masked, "from *******   to *************** | done");
+        assert_eq!(masked.len(), line.len());
+    }
+
+    #[test]
+    fn test_filter_ips_audited() {
+        let mut censored = Vec::new();
+
+        assert_eq!(
+            filter_ips_audited("from 1.2.3.4 to 10.0.0.1", |ip| censored
+                .push(ip.to_owned())),
+            "from -censored- to -censored-"
+        );
+        assert_eq!(censored, ["1.2.3.4", "10.0.0.1"]);
+    }
+
+    #[test]
+    fn test_find_ips() {
+        assert_eq!(
+            find_ips("from 1.2.3.4 to 10.0.0.1, then 1.2.3.4 again").collect::<Vec<_>>(),
+            ["1.2.3.4", "10.0.0.1", "1.2.3.4"]
+        );
+        assert_eq!(find_ips("version 515.1630").count(), 0);
+    }
+
+    #[test]
+    fn test_collect_ips() {
+        assert_eq!(
+            collect_ips(
+                "from 10.0.0.1 to 1.2.3.4\nthen 1.2.3.4 again, and 10.0.0.1, and 8.8.8.8\nversion 515.1630"
+            ),
+            ["10.0.0.1", "1.2.3.4", "8.8.8.8"]
+        );
+        assert!(collect_ips("from -censored-").is_empty());
+    }
+
+    #[test]
+    fn test_contains_ip() {
+        assert!(contains_ip("from 1.2.3.4"));
+        assert!(contains_ip("255.255.255.255"));
+        assert!(!contains_ip("from -censored-"));
+        assert!(!contains_ip("version 515.1630"));
+        assert!(!contains_ip(&filter_ips("from 1.2.3.4 to 10.0.0.1")));
+    }
+
+    #[test]
+    fn test_filter_ips_except() {
+        let allow = HashSet::from([Ipv4Addr::new(10, 0, 0, 5), Ipv4Addr::new(192, 168, 1, 1)]);
+
+        assert_eq!(
+            filter_ips_except(
+                "monitor 10.0.0.5 saw 1.2.3.4 and 10.0.0.6 from 192.168.1.1",
+                &allow
+            ),
+            "monitor 10.0.0.5 saw -censored- and -censored- from 192.168.1.1"
+        );
+        assert_eq!(
+            filter_ips_except("from 1.2.3.4", &HashSet::new()),
+            "from -censored-"
+        );
+        assert_eq!(filter_ips_except("only 10.0.0.5", &allow), "only 10.0.0.5");
+
+        // Zero padded addresses are the same address, and are still censored if they aren't allowed
+        assert_eq!(
+            filter_ips_except(
+                "from 192.168.001.001 and 010.000.000.005 and 001.002.003.004",
+                &allow
+            ),
+            "from 192.168.001.001 and 010.000.000.005 and -censored-"
+        );
+    }
+
+    #[test]
+    fn test_zero_padded_ips() {
+        assert_eq!(
+            filter_ips("from 192.168.001.001 to 010.1.1.1"),
+            "from -censored- to -censored-"
+        );
+        assert_eq!(
+            find_ips("from 192.168.001.001").collect::<Vec<_>>(),
+            ["192.168.001.001"]
+        );
+        assert_eq!(
+            filter_public_ips("relay 010.000.000.005"),
+            "relay 010.000.000.005"
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv4() {
+        assert_eq!(
+            parse_ipv4("192.168.001.001"),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!(parse_ipv4("192.168.1.1"), parse_ipv4("192.168.001.001"));
+
+        for invalid in [
+            "192.168.1",
+            "192.168.1.1.1",
+            "192.168.1.256",
+            "192.168.+1.1",
+            "192..1.1",
+        ] {
+            assert_eq!(parse_ipv4(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_filter_public_ips() {
+        for internal in [
+            "10.0.0.1",
+            "10.255.255.255",
+            "172.16.0.1",
+            "172.31.255.255",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.10.20",
+        ] {
+            assert_eq!(filter_public_ips(internal), internal);
+        }
+
+        for public in [
+            "8.8.8.8",
+            "172.15.0.1",
+            "172.32.0.1",
+            "192.169.1.1",
+            "11.0.0.1",
+        ] {
+            assert_eq!(filter_public_ips(public), "-censored-");
+        }
+
+        assert_eq!(
+            filter_public_ips("relay 10.0.0.5 forwarded 203.0.113.7"),
+            "relay 10.0.0.5 forwarded -censored-"
+        );
+    }
+}
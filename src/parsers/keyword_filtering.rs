@@ -0,0 +1,50 @@
+use std::borrow::Cow;
+
+use regex::{Regex, RegexBuilder};
+
+// Case insensitive, whole words only, so "ass" doesn't catch "grass"
+pub fn keyword_regex(keywords: &[String]) -> Option<Regex> {
+    if keywords.is_empty() {
+        return None;
+    }
+
+    let alternatives = keywords
+        .iter()
+        .map(|keyword| regex::escape(keyword))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    Some(
+        RegexBuilder::new(&format!(r"\b(?:{alternatives})\b"))
+            .case_insensitive(true)
+            .build()
+            .expect("escaped keywords should always be a valid regex"),
+    )
+}
+
+pub fn filter_keywords<'a>(contents: &'a str, keyword_regex: Option<&Regex>) -> Cow<'a, str> {
+    match keyword_regex {
+        Some(keyword_regex) => keyword_regex.replace_all(contents, "-censored(keyword)-"),
+        None => Cow::Borrowed(contents),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_keywords() {
+        let keyword_regex = keyword_regex(&["secret".to_owned(), "a.b".to_owned()]);
+
+        assert_eq!(
+            filter_keywords(
+                "the Secret code is secretive, not a.b or axb",
+                keyword_regex.as_ref()
+            ),
+            "the -censored(keyword)- code is secretive, not -censored(keyword)- or axb"
+        );
+
+        assert_eq!(filter_keywords("the secret", None), "the secret");
+    }
+}
@@ -1,128 +1,1286 @@
-use std::{ffi::OsStr, path::Path};
+use std::{borrow::Cow, collections::HashSet, ffi::OsStr, path::Path};
 
-mod game;
-mod ip_filtering;
+pub mod audit;
+pub mod base64_filtering;
+pub mod comms;
+pub mod coordinate_filtering;
+pub mod game;
+pub mod html;
+pub mod ip_filtering;
+pub mod keyword_filtering;
+pub mod paper;
+mod player_content;
+pub mod round_end_data;
 pub mod runtimes;
+pub mod url_filtering;
+
+// See Strategy::as_bytes_fn
+pub type BytesStrategyFn = for<'a> fn(&'a [u8]) -> Cow<'a, [u8]>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    GameLog,
+    RuntimeLog,
+    HtmlLog,
+    PaperLog,
+    PaperLogJson,
+    CommsLog,
+    CommsLogJson,
+    RoundEndDataJson,
+    RoundEndDataHtml,
+    PassthroughSanitized,
+    Identity,
+}
+
+impl Strategy {
+    // Also what the strategy is called in a round's override file
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::GameLog => "game",
+            Strategy::RuntimeLog => "runtime",
+            Strategy::HtmlLog => "html",
+            Strategy::PaperLog => "paper",
+            Strategy::PaperLogJson => "paper_json",
+            Strategy::CommsLog => "comms",
+            Strategy::CommsLogJson => "comms_json",
+            Strategy::RoundEndDataJson => "round_end_data_json",
+            Strategy::RoundEndDataHtml => "round_end_data_html",
+            Strategy::PassthroughSanitized => "passthrough_sanitized",
+            Strategy::Identity => "identity",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Strategy::GameLog,
+            Strategy::RuntimeLog,
+            Strategy::HtmlLog,
+            Strategy::PaperLog,
+            Strategy::PaperLogJson,
+            Strategy::CommsLog,
+            Strategy::CommsLogJson,
+            Strategy::RoundEndDataJson,
+            Strategy::RoundEndDataHtml,
+            Strategy::PassthroughSanitized,
+            Strategy::Identity,
+        ]
+        .into_iter()
+        .find(|strategy| strategy.name() == name)
+    }
+
+    // Where the sanitized file is published, relative to the output root.
+    // Passed through text logs always come out as .log, even when the server wrote them as .txt.
+    pub fn output_path(self, relative_path: &Path) -> Cow<'_, Path> {
+        match self {
+            Strategy::PassthroughSanitized
+                if relative_path
+                    .extension()
+                    .is_some_and(|extension| extension == "txt") =>
+            {
+                Cow::Owned(relative_path.with_extension("log"))
+            }
+
+            _ => Cow::Borrowed(relative_path),
+        }
+    }
+
+    // The strategy with the default policy
+    pub fn as_fn(self) -> fn(String) -> String {
+        match self {
+            Strategy::GameLog => game::process_game_log,
+            Strategy::RuntimeLog => runtimes::process_runtimes_log,
+            Strategy::HtmlLog => html::process_html_log,
+            Strategy::PaperLog => paper::process_paper_log,
+            Strategy::RoundEndDataJson => round_end_data::process_round_end_data_json,
+            Strategy::RoundEndDataHtml => round_end_data::process_round_end_data_html,
+            Strategy::PaperLogJson
+            | Strategy::CommsLog
+            | Strategy::CommsLogJson
+            | Strategy::PassthroughSanitized => {
+                |contents| apply_filter(contents, ip_filtering::filter_ips)
+            }
+            Strategy::Identity => std::convert::identity,
+        }
+    }
+
+    // The strategy with the default policy, for passthrough files that don't need to be UTF-8.
+    // Skips reading them into a String and back. None for strategies that parse their contents.
+    pub fn as_bytes_fn(self) -> Option<BytesStrategyFn> {
+        match self {
+            Strategy::PaperLog
+            | Strategy::PaperLogJson
+            | Strategy::CommsLog
+            | Strategy::CommsLogJson
+            | Strategy::PassthroughSanitized => Some(ip_filtering::filter_ips_bytes),
+            Strategy::Identity => Some(|contents| Cow::Borrowed(contents)),
+            Strategy::GameLog
+            | Strategy::RuntimeLog
+            | Strategy::HtmlLog
+            | Strategy::RoundEndDataJson
+            | Strategy::RoundEndDataHtml => None,
+        }
+    }
+
+    pub fn sanitize(self, path: &Path, contents: String, policy: &SanitizationPolicy) -> String {
+        let filename = path.file_name().and_then(OsStr::to_str).unwrap_or("");
+
+        match self {
+            Strategy::GameLog => game::process_game_log_with_policy(contents, &policy.game),
+            Strategy::RuntimeLog => {
+                runtimes::process_runtimes_log_with_policy(&contents, &policy.runtime)
+            }
+            Strategy::HtmlLog => {
+                html::process_html_log_with_policy(&contents, filename, &policy.passthrough)
+            }
+
+            // Unless the content is being censored, paper logs are like any other, but with IPs filtered
+            // out of the JSON too, since what players write can have anything in it.
+            // The keyword blocklist still applies, the same as when it is.
+            Strategy::PaperLog | Strategy::PaperLogJson if !policy.paper.censor_content => policy
+                .passthrough
+                .filter_keywords(Strategy::PassthroughSanitized.sanitize(path, contents, policy)),
+            Strategy::PaperLog => policy.passthrough.apply(
+                filename,
+                paper::process_paper_log_with_policy(
+                    &contents,
+                    &policy.paper,
+                    &policy.passthrough.keyword_blocklist,
+                ),
+            ),
+            Strategy::PaperLogJson => policy.passthrough.apply(
+                filename,
+                paper::process_paper_log_json_with_policy(
+                    &contents,
+                    &policy.paper,
+                    &policy.passthrough.keyword_blocklist,
+                ),
+            ),
+
+            // Same for comms logs
+            Strategy::CommsLog | Strategy::CommsLogJson if !policy.comms.censor_messages => policy
+                .passthrough
+                .filter_keywords(Strategy::PassthroughSanitized.sanitize(path, contents, policy)),
+            Strategy::CommsLog => policy.passthrough.apply(
+                filename,
+                comms::process_comms_log_with_policy(
+                    &contents,
+                    &policy.comms,
+                    &policy.passthrough.keyword_blocklist,
+                ),
+            ),
+            Strategy::CommsLogJson => policy.passthrough.apply(
+                filename,
+                comms::process_comms_log_json_with_policy(
+                    &contents,
+                    &policy.comms,
+                    &policy.passthrough.keyword_blocklist,
+                ),
+            ),
+
+            Strategy::RoundEndDataJson => policy.passthrough.apply(
+                filename,
+                round_end_data::process_round_end_data_json_with_policy(
+                    &contents,
+                    &policy.round_end_data,
+                ),
+            ),
+            Strategy::RoundEndDataHtml => round_end_data::process_round_end_data_html_with_policy(
+                &contents,
+                filename,
+                &policy.round_end_data,
+                &policy.passthrough,
+            ),
+
+            Strategy::PassthroughSanitized => {
+                let ip_censor_context = policy
+                    .passthrough
+                    .label_ip_censors
+                    .then_some(ip_filtering::IpCensorContext::Passthrough);
+
+                policy.passthrough.apply(
+                    filename,
+                    apply_filter(contents, |contents| {
+                        ip_filtering::filter_ips_in(contents, ip_censor_context)
+                    }),
+                )
+            }
+            Strategy::Identity => contents,
+        }
+    }
+
+    // For output that's already been sanitized, such as when tightening the rules for logs that
+    // have already been published. Running it on its own output never changes anything.
+    // - Game logs keep lines that were already censored whole, rather than censoring them again as no_ts_start.
+    //   Everything else they do is idempotent.
+    // - Runtime logs, HTML text, paper content, and passthrough filters never match their own censor text,
+    //   so they're run as usual.
+    //   The exception is a keyword blocklist with a word like "censored" in it.
+    pub fn resanitize(self, path: &Path, contents: String, policy: &SanitizationPolicy) -> String {
+        match self {
+            Strategy::GameLog => game::resanitize_game_log_with_policy(&contents, &policy.game),
+            Strategy::RuntimeLog
+            | Strategy::HtmlLog
+            | Strategy::PaperLog
+            | Strategy::PaperLogJson
+            | Strategy::CommsLog
+            | Strategy::CommsLogJson
+            | Strategy::RoundEndDataJson
+            | Strategy::RoundEndDataHtml
+            | Strategy::PassthroughSanitized
+            | Strategy::Identity => self.sanitize(path, contents, policy),
+        }
+    }
+
+    // Like sanitize, but reports everything censored to the sink.
+    // Only game and runtime logs report anything so far, the rest sanitize as normal.
+    pub fn sanitize_audited(
+        self,
+        path: &Path,
+        contents: String,
+        policy: &SanitizationPolicy,
+        sink: &mut dyn audit::AuditSink,
+    ) -> String {
+        let file = path.to_string_lossy();
+
+        match self {
+            Strategy::GameLog => {
+                game::process_game_log_audited(&contents, &policy.game, &file, sink)
+            }
+            Strategy::RuntimeLog => {
+                runtimes::process_runtimes_log_audited(&contents, &policy.runtime, &file, sink)
+            }
+            Strategy::HtmlLog
+            | Strategy::PaperLog
+            | Strategy::PaperLogJson
+            | Strategy::CommsLog
+            | Strategy::CommsLogJson
+            | Strategy::RoundEndDataJson
+            | Strategy::RoundEndDataHtml
+            | Strategy::PassthroughSanitized
+            | Strategy::Identity => self.sanitize(path, contents, policy),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SanitizationPolicy {
+    pub game: game::GameLogPolicy,
+    pub runtime: runtimes::RuntimeLogPolicy,
+    pub passthrough: PassthroughPolicy,
+    pub paper: paper::PaperLogPolicy,
+    pub comms: comms::CommsLogPolicy,
+    pub round_end_data: round_end_data::RoundEndDataPolicy,
+    pub strict_mode: StrictMode,
+
+    // Publish files we don't know about by their extension rather than leaving them out, see extension_fallback_strategy.
+    // Strict mode still leaves them out.
+    pub extension_fallback: bool,
+}
+
+impl SanitizationPolicy {
+    pub fn strict() -> Self {
+        let mut policy = Self::default();
+        policy.apply_strict_mode();
+        policy
+    }
+
+    pub fn apply_strict_mode(&mut self) {
+        self.game.apply_strict_mode();
+        self.strict_mode = StrictMode::On;
+    }
+
+    // Censor IPs with where they were censored, like -censored(ip:game)-, for everything that has a policy here
+    pub fn label_ip_censors(&mut self) {
+        self.game.label_ip_censors = true;
+        self.runtime.label_ip_censors = true;
+        self.passthrough.label_ip_censors = true;
+    }
+
+    // Like get_sanitization_strategy, but respecting strict mode and extension_fallback
+    pub fn sanitization_strategy(&self, path: &Path) -> Option<Strategy> {
+        let strategy = get_sanitization_strategy(path).or_else(|| {
+            self.extension_fallback
+                .then(|| extension_fallback_strategy(path))
+                .flatten()
+        })?;
+
+        if self.strict_mode == StrictMode::On {
+            let filename = path.file_name().and_then(OsStr::to_str)?;
+            if !KNOWN_FILENAMES.contains(&filename) {
+                return None;
+            }
+        }
+
+        Some(strategy)
+    }
+}
+
+// For operators who would rather censor too much than too little. Turning it on:
+// - censors game log categories without special handling, rather than allowing them
+// - censors game log lines without a full date and millisecond timestamp
+// - censors game log lines without a timestamp, even with continuation_lines
+// - censors every ADMINPRIVATE line, even with an allowlist
+// - censors GAME-COMPAT: lines with nothing after them, even with allow_empty_game_compat
+// - censors URLs in game logs entirely
+// - only publishes files in known_filenames(), not perf- files or the profiler folder
+// Lines that don't parse are censored whether or not it's on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StrictMode {
+    #[default]
+    Off,
+    On,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PassthroughPolicy {
+    // Filenames to mask (x, y, z) coordinates in, for servers where those reveal player bases
+    pub mask_coordinates_in: HashSet<String>,
+
+    // Words to censor in the text of HTML logs, and in player content like paper logs
+    pub keyword_blocklist: Vec<String>,
+
+    // Censor runs of base64 at least this long, 64 is a good start
+    pub base64_min_length: Option<usize>,
+
+    // Censor IPs in HTML and passthrough_sanitized files as -censored(ip:html)- and
+    // -censored(ip:passthrough)- rather than -censored-, to tell where they were censored
+    pub label_ip_censors: bool,
+}
+
+impl PassthroughPolicy {
+    // The filters that were asked for by filename or turned on. Identity files don't go through them, since
+    // those are published untouched.
+    fn apply(&self, filename: &str, mut contents: String) -> String {
+        if self.mask_coordinates_in.contains(filename) {
+            contents = apply_filter(contents, coordinate_filtering::filter_coordinates);
+        }
+
+        if let Some(min_length) = self.base64_min_length {
+            contents = apply_filter(contents, |contents| {
+                base64_filtering::filter_base64_blobs(contents, min_length)
+            });
+        }
+
+        contents
+    }
+
+    fn filter_keywords(&self, contents: String) -> String {
+        let keyword_regex = keyword_filtering::keyword_regex(&self.keyword_blocklist);
+
+        apply_filter(contents, |contents| {
+            keyword_filtering::filter_keywords(contents, keyword_regex.as_ref())
+        })
+    }
+}
+
+// Only copies the contents if the filter actually changed something
+fn apply_filter(contents: String, filter: impl FnOnce(&str) -> Cow<'_, str>) -> String {
+    let filtered = match filter(&contents) {
+        Cow::Owned(filtered) => Some(filtered),
+        Cow::Borrowed(_) => None,
+    };
+
+    filtered.unwrap_or(contents)
+}
+
+// Some uploaded logs start with a UTF-8 byte order mark, which would otherwise end up in the first line
+pub(crate) fn strip_bom(contents: &str) -> &str {
+    contents.strip_prefix('\u{feff}').unwrap_or(contents)
+}
+
+// Every exact filename that has a sanitization strategy.
+// Anything not game.log or runtime.log is passed through.
+static KNOWN_FILENAMES: &[&str] = &[
+    "game.log",
+    "runtime.log",
+    "asset.log",
+    "asset.log.json",
+    "atmos.html",
+    "attack.log.json",
+    "attack.log",
+    "botany.html",
+    "cargo.html",
+    "circuit.html",
+    "cloning.log.json",
+    "cloning.log",
+    "crafting.html",
+    "deaths.html",
+    "dynamic.json",
+    "dynamic.log.json",
+    "dynamic.log",
+    "econ.log.json",
+    "econ.log",
+    "economy.log.json",
+    "economy.log",
+    "engine.html",
+    "experimentor.html",
+    "gravity.html",
+    "hallucinations.html",
+    "harddel.log.json",
+    "harddel.log",
+    "harddels.log.json",
+    "harddels.log",
+    "hypertorus.html",
+    "id_card_changes.html",
+    "init_profiler.json",
+    "init_times.json",
+    "initialize.log.json",
+    "initialize.log",
+    "job_debug.log.json",
+    "job_debug.log",
+    "kudzu.html",
+    "manifest.log.json",
+    "manifest.log",
+    "map_errors.log.json",
+    "map_errors.log",
+    "mecha.log.json",
+    "mecha.log",
+    "mob_tags.log.json",
+    "mob_tags.log",
+    "nanites.html",
+    "newscaster.json",
+    "overlay.log.json",
+    "overlay.log",
+    "paper.log.json",
+    "paper.log",
+    "pda.log.json",
+    "pda.log",
+    "portals.html",
+    "presents.html",
+    "profiler.json",
+    "qdel.log.json",
+    "qdel.log",
+    "radiation.html",
+    "records.html",
+    "research.html",
+    "round_end_data.html",
+    "round_end_data.json",
+    "sendmaps.json",
+    "shuttle.log.json",
+    "shuttle.log",
+    "signal.log.json",
+    "signal.log",
+    "signals.log.json",
+    "signals.log",
+    "silicon.log.json",
+    "silicon.log",
+    "silo.json",
+    "silo.log.json",
+    "silo.log",
+    "singulo.html",
+    "speech_indicators.log.json",
+    "speech_indicators.log",
+    "supermatter.html",
+    "target_zone_switch.json",
+    "telecomms.log.json",
+    "telecomms.log",
+    "telesci.html",
+    "tool.log.json",
+    "tool.log",
+    "tools.log.json",
+    "tools.log",
+    "uplink.log.json",
+    "uplink.log",
+    "virus.log.json",
+    "virus.log",
+    "wires.html",
+];
+
+pub fn known_filenames() -> &'static [&'static str] {
+    KNOWN_FILENAMES
+}
 
 // Given a path, returns a function that will take the contents of that file and return the sanitized version.
 pub fn get_file_sanitization_strategy(path: &Path) -> Option<fn(String) -> String> {
+    get_sanitization_strategy(path).map(Strategy::as_fn)
+}
+
+pub fn get_sanitization_strategy(path: &Path) -> Option<Strategy> {
     let filename = path.file_name().and_then(OsStr::to_str)?;
 
     match filename {
-        "game.log" => Some(game::process_game_log),
+        "game.log" => Some(Strategy::GameLog),
 
         // Runtime condensing is done in the runtimes.rs parser
-        "runtime.log" => Some(runtimes::process_runtimes_log),
-
-        // Pass through, but replace .txt with .log
-        "asset.log"
-        | "asset.log.json"
-        | "atmos.html"
-        | "attack.log.json"
-        | "attack.log"
-        | "botany.html"
-        | "cargo.html"
-        | "circuit.html"
-        | "cloning.log.json"
-        | "cloning.log"
-        | "crafting.html"
-        | "deaths.html"
-        | "dynamic.json"
-        | "dynamic.log.json"
-        | "dynamic.log"
-        | "econ.log.json"
-        | "econ.log"
-        | "economy.log.json"
-        | "economy.log"
-        | "engine.html"
-        | "experimentor.html"
-        | "gravity.html"
-        | "hallucinations.html"
-        | "harddel.log.json"
-        | "harddel.log"
-        | "harddels.log.json"
-        | "harddels.log"
-        | "hypertorus.html"
-        | "id_card_changes.html"
-        | "init_profiler.json"
-        | "init_times.json"
-        | "initialize.log.json"
-        | "initialize.log"
-        | "job_debug.log.json"
-        | "job_debug.log"
-        | "kudzu.html"
-        | "manifest.log.json"
-        | "manifest.log"
-        | "map_errors.log.json"
-        | "map_errors.log"
-        | "mecha.log.json"
-        | "mecha.log"
-        | "mob_tags.log.json"
-        | "mob_tags.log"
-        | "nanites.html"
-        | "newscaster.json"
-        | "overlay.log.json"
-        | "overlay.log"
-        | "paper.log.json"
-        | "paper.log"
-        | "pda.log.json"
-        | "pda.log"
-        | "portals.html"
-        | "presents.html"
-        | "profiler.json"
-        | "qdel.log.json"
-        | "qdel.log"
-        | "radiation.html"
-        | "records.html"
-        | "research.html"
-        | "round_end_data.html"
-        | "round_end_data.json"
-        | "sendmaps.json"
-        | "shuttle.log.json"
-        | "shuttle.log"
-        | "signal.log.json"
-        | "signal.log"
-        | "signals.log.json"
-        | "signals.log"
-        | "silicon.log.json"
-        | "silicon.log"
-        | "silo.json"
-        | "silo.log.json"
-        | "silo.log"
-        | "singulo.html"
-        | "speech_indicators.log.json"
-        | "speech_indicators.log"
-        | "supermatter.html"
-        | "target_zone_switch.json"
-        | "telecomms.log.json"
-        | "telecomms.log"
-        | "telesci.html"
-        | "tool.log.json"
-        | "tool.log"
-        | "tools.log.json"
-        | "tools.log"
-        | "uplink.log.json"
-        | "uplink.log"
-        | "virus.log.json"
-        | "virus.log"
-        | "wires.html" => Some(std::convert::identity),
-
-        perf_filename if perf_filename.starts_with("perf-") => Some(std::convert::identity),
-
-        profiler_file
+        "runtime.log" => Some(Strategy::RuntimeLog),
+
+        // The round summary, which can have connection details in it
+        "round_end_data.json" => Some(Strategy::RoundEndDataJson),
+        "round_end_data.html" => Some(Strategy::RoundEndDataHtml),
+
+        // HTML logs only have their text sanitized
+        html_filename
+            if html_filename.ends_with(".html") && KNOWN_FILENAMES.contains(&html_filename) =>
+        {
+            Some(Strategy::HtmlLog)
+        }
+
+        // Free-form player writing, which operators can have censored
+        "paper.log" => Some(Strategy::PaperLog),
+        "paper.log.json" => Some(Strategy::PaperLogJson),
+
+        // Radio and PDA messages, which operators can have censored too
+        "signal.log" | "signals.log" | "telecomms.log" => Some(Strategy::CommsLog),
+        "signal.log.json" | "signals.log.json" | "telecomms.log.json" => {
+            Some(Strategy::CommsLogJson)
+        }
+
+        // Plain text logs are passed through with IPs still filtered, since anything can end up in them
+        text_filename
+            if text_filename.ends_with(".log") && KNOWN_FILENAMES.contains(&text_filename) =>
+        {
+            Some(Strategy::PassthroughSanitized)
+        }
+
+        // Some servers write these as .txt, which are passed through the same way, but published as .log.
+        // See Strategy::output_path.
+        txt_filename
+            if txt_filename.ends_with(".txt")
+                && get_sanitization_strategy(&path.with_extension("log"))
+                    == Some(Strategy::PassthroughSanitized) =>
+        {
+            Some(Strategy::PassthroughSanitized)
+        }
+
+        // JSON is passed through untouched, where numbers that look like IPs can still matter
+        known_filename if KNOWN_FILENAMES.contains(&known_filename) => Some(Strategy::Identity),
+
+        perf_filename if perf_filename.starts_with("perf-") => Some(Strategy::Identity),
+
+        _profiler_file
             if path
                 .parent()
                 .is_some_and(|p| p.file_name().is_some_and(|pname| pname == "profiler")) =>
         {
-            Some(std::convert::identity)
+            Some(Strategy::Identity)
         }
 
         _ => None,
     }
 }
 
-// Separate so we can tracy it
+// For files that aren't known, but that we can still sanitize going by their extension.
+// JSON gets its IPs filtered as text, unlike known JSON, since we don't know what's in it.
+pub fn extension_fallback_strategy(path: &Path) -> Option<Strategy> {
+    match path.extension().and_then(OsStr::to_str)? {
+        "log" | "json" => Some(Strategy::PassthroughSanitized),
+        "html" => Some(Strategy::HtmlLog),
+        _ => None,
+    }
+}
+
+// For contents that are only in memory, such as in a browser. Only the filename is needed,
+// so files that are only published because of their folder, like the profiler's, are left out.
+// None if the file would never be published.
+pub fn sanitize_contents(filename: &str, contents: String) -> Option<String> {
+    sanitize_contents_with_policy(filename, contents, &SanitizationPolicy::default())
+}
+
+pub fn sanitize_contents_with_policy(
+    filename: &str,
+    contents: String,
+    policy: &SanitizationPolicy,
+) -> Option<String> {
+    let path = Path::new(filename);
+    let strategy = policy.sanitization_strategy(path)?;
+    Some(strategy.sanitize(path, contents, policy))
+}
+
+// The sanitized contents along with their SHA-256, for noticing when a rules change
+// produces different output. None if the file would never be published.
+#[cfg(feature = "server")]
+pub fn sanitize_and_hash(path: &Path) -> eyre::Result<Option<(String, [u8; 32])>> {
+    use sha2::Digest;
+
+    let Some(strategy) = get_file_sanitization_strategy(path) else {
+        return Ok(None);
+    };
+
+    let sanitized = strategy(read_to_string(path, None)?);
+    let hash = sha2::Sha256::digest(sanitized.as_bytes()).into();
+
+    Ok(Some((sanitized, hash)))
+}
+
+// Same as sanitize_and_hash, but reading with tokio::fs
+#[cfg(feature = "server")]
+pub async fn sanitize_and_hash_async(path: &Path) -> eyre::Result<Option<(String, [u8; 32])>> {
+    use sha2::Digest;
+
+    let Some(strategy) = get_file_sanitization_strategy(path) else {
+        return Ok(None);
+    };
+
+    let sanitized = strategy(read_to_string_async(path, None).await?);
+    let hash = sha2::Sha256::digest(sanitized.as_bytes()).into();
+
+    Ok(Some((sanitized, hash)))
+}
+
+// Separate so we can tracy it. Files over max_size are refused with FileTooLarge rather than read,
+// so that a runaway log can't use up all our memory.
+#[cfg(feature = "server")]
+#[tracing::instrument(skip_all)]
+pub(crate) fn read_to_string(path: &Path, max_size: Option<u64>) -> std::io::Result<String> {
+    use std::io::Read;
+
+    let Some(max_size) = max_size else {
+        return std::fs::read_to_string(path);
+    };
+
+    let file = std::fs::File::open(path)?;
+
+    let size = file.metadata()?.len();
+    if size > max_size {
+        return Err(FileTooLarge { size, max_size }.into());
+    }
+
+    // It could still be growing
+    let mut contents = Vec::with_capacity(size as usize);
+    file.take(max_size + 1).read_to_end(&mut contents)?;
+    limited_contents_to_string(contents, max_size)
+}
+
+// Same as read_to_string, but with tokio::fs so it doesn't block the runtime
+#[cfg(feature = "server")]
 #[tracing::instrument(skip_all)]
-fn read_to_string(path: &Path) -> std::io::Result<String> {
-    std::fs::read_to_string(path)
+pub(crate) async fn read_to_string_async(
+    path: &Path,
+    max_size: Option<u64>,
+) -> std::io::Result<String> {
+    use tokio::io::AsyncReadExt;
+
+    let Some(max_size) = max_size else {
+        return tokio::fs::read_to_string(path).await;
+    };
+
+    let file = tokio::fs::File::open(path).await?;
+
+    let size = file.metadata().await?.len();
+    if size > max_size {
+        return Err(FileTooLarge { size, max_size }.into());
+    }
+
+    let mut contents = Vec::with_capacity(size as usize);
+    file.take(max_size + 1).read_to_end(&mut contents).await?;
+    limited_contents_to_string(contents, max_size)
+}
+
+// contents were read up to one byte past max_size, to tell if there was more
+#[cfg(feature = "server")]
+fn limited_contents_to_string(contents: Vec<u8>, max_size: u64) -> std::io::Result<String> {
+    if contents.len() as u64 > max_size {
+        return Err(FileTooLarge {
+            size: contents.len() as u64,
+            max_size,
+        }
+        .into());
+    }
+
+    String::from_utf8(contents)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+}
+
+// Comes wrapped in an io::Error with ErrorKind::FileTooLarge
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileTooLarge {
+    // At least this many bytes
+    pub size: u64,
+    pub max_size: u64,
+}
+
+impl std::fmt::Display for FileTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "file is {} bytes, over the limit of {}",
+            self.size, self.max_size
+        )
+    }
+}
+
+impl std::error::Error for FileTooLarge {}
+
+impl From<FileTooLarge> for std::io::Error {
+    fn from(error: FileTooLarge) -> Self {
+        std::io::Error::new(std::io::ErrorKind::FileTooLarge, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // Every folder in fixtures/ is a small, made up round folder.
+    // Each file in it that has a sanitization strategy is compared against
+    // the file with the same name plus ".expected".
+    // runtime.log is also compared against runtime.condensed.{txt,json}.expected.
+    const FIXTURES_PATH: &str = "fixtures";
+
+    fn expected_path(path: &Path, name: &str) -> PathBuf {
+        path.with_file_name(format!("{name}.expected"))
+    }
+
+    fn assert_matches_fixture(expected_path: &Path, actual: &str) {
+        let expected = std::fs::read_to_string(expected_path)
+            .unwrap_or_else(|error| panic!("couldn't read {}: {error}", expected_path.display()));
+
+        if expected != actual {
+            panic!(
+                "{} didn't match\n\nexpected:\n{expected}\n\nactual:\n{actual}",
+                expected_path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_mask_coordinates_only_in_configured_files() {
+        let policy = SanitizationPolicy {
+            passthrough: PassthroughPolicy {
+                mask_coordinates_in: HashSet::from(["silo.log".to_owned()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let contents = "Somebody took 5 iron at the silo (123, 45, 2)".to_owned();
+
+        assert_eq!(
+            Strategy::PassthroughSanitized.sanitize(
+                Path::new("round-1/silo.log"),
+                contents.clone(),
+                &policy
+            ),
+            "Somebody took 5 iron at the silo -censored(coords)-"
+        );
+        assert_eq!(
+            Strategy::PassthroughSanitized.sanitize(
+                Path::new("round-1/econ.log"),
+                contents.clone(),
+                &policy
+            ),
+            contents
+        );
+    }
+
+    #[test]
+    fn test_identity_is_untouched() {
+        let policy = SanitizationPolicy {
+            passthrough: PassthroughPolicy {
+                mask_coordinates_in: HashSet::from(["attack.log.json".to_owned()]),
+                base64_min_length: Some(32),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let contents =
+            r#"{"at":"(1, 2, 3)","blob":"eyJuYW1lIjoiU29tZSBCb2R5IiwiaXAiOiIxLjIuMy40In0="}"#
+                .to_owned();
+
+        assert_eq!(
+            Strategy::Identity.sanitize(
+                Path::new("round-1/attack.log.json"),
+                contents.clone(),
+                &policy
+            ),
+            contents
+        );
+    }
+
+    #[test]
+    fn test_passthrough_sanitized_filters_ips() {
+        let contents = "Somebody connected from 1.2.3.4".to_owned();
+        let policy = SanitizationPolicy::default();
+
+        assert_eq!(
+            Strategy::PassthroughSanitized.sanitize(
+                Path::new("round-1/attack.log"),
+                contents.clone(),
+                &policy
+            ),
+            "Somebody connected from -censored-"
+        );
+        assert_eq!(
+            Strategy::Identity.sanitize(
+                Path::new("round-1/attack.log.json"),
+                contents.clone(),
+                &policy
+            ),
+            contents
+        );
+    }
+
+    #[test]
+    fn test_as_bytes_fn() {
+        let contents = b"\xff from 1.2.3.4";
+
+        let identity = Strategy::Identity.as_bytes_fn().unwrap();
+        assert!(matches!(identity(contents), Cow::Borrowed(borrowed) if borrowed == contents));
+
+        let passthrough = Strategy::PassthroughSanitized.as_bytes_fn().unwrap();
+        assert_eq!(passthrough(contents).as_ref(), b"\xff from -censored-");
+
+        assert!(Strategy::GameLog.as_bytes_fn().is_none());
+    }
+
+    #[test]
+    fn test_player_content_json_filters_ips_by_default() {
+        let contents = "{\"message\":\"call me at 1.2.3.4\"}\n";
+        let censored = "{\"message\":\"call me at -censored-\"}\n";
+
+        for (filename, strategy) in [
+            ("paper.log.json", Strategy::PaperLogJson),
+            ("signal.log.json", Strategy::CommsLogJson),
+            ("signals.log.json", Strategy::CommsLogJson),
+            ("telecomms.log.json", Strategy::CommsLogJson),
+        ] {
+            let path = Path::new("round-1").join(filename);
+            assert_eq!(get_sanitization_strategy(&path), Some(strategy));
+
+            assert_eq!(
+                strategy.sanitize(&path, contents.to_owned(), &SanitizationPolicy::default()),
+                censored,
+                "{filename}"
+            );
+            assert_eq!(
+                strategy.as_fn()(contents.to_owned()),
+                censored,
+                "{filename}"
+            );
+            assert_eq!(
+                strategy.as_bytes_fn().unwrap()(contents.as_bytes()).as_ref(),
+                censored.as_bytes(),
+                "{filename}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_player_content_keywords_without_censoring() {
+        let policy = SanitizationPolicy {
+            passthrough: PassthroughPolicy {
+                keyword_blocklist: vec!["syndicate".to_owned()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        for (filename, contents, censored) in [
+            (
+                "paper.log",
+                "[12:00:00] PAPER: wrote \"Syndicate\" from 1.2.3.4\n",
+                "[12:00:00] PAPER: wrote \"-censored(keyword)-\" from -censored-\n",
+            ),
+            (
+                "paper.log.json",
+                "{\"message\":\"the syndicate\"}\n",
+                "{\"message\":\"the -censored(keyword)-\"}\n",
+            ),
+            (
+                "telecomms.log",
+                "[12:00:00] TCOMMS: Somebody [Common] \"syndicate from 1.2.3.4\"\n",
+                "[12:00:00] TCOMMS: Somebody [Common] \"-censored(keyword)- from -censored-\"\n",
+            ),
+            (
+                "signal.log",
+                "[12:00:00] SIGNAL: Somebody pulsed the Syndicate door\n",
+                "[12:00:00] SIGNAL: Somebody pulsed the -censored(keyword)- door\n",
+            ),
+            (
+                "signal.log.json",
+                "{\"message\":\"the syndicate\"}\n",
+                "{\"message\":\"the -censored(keyword)-\"}\n",
+            ),
+            (
+                "telecomms.log.json",
+                "{\"message\":\"the syndicate\"}\n",
+                "{\"message\":\"the -censored(keyword)-\"}\n",
+            ),
+        ] {
+            let path = Path::new("round-1").join(filename);
+
+            assert_eq!(
+                policy.sanitization_strategy(&path).unwrap().sanitize(
+                    &path,
+                    contents.to_owned(),
+                    &policy
+                ),
+                censored,
+                "{filename}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sanitize_contents() {
+        assert_eq!(
+            sanitize_contents(
+                "game.log",
+                "[2024-01-02 03:04:05.678] TOPIC: from 1.2.3.4\n".to_owned()
+            )
+            .as_deref(),
+            Some("-censored(world_topic logs)-\n")
+        );
+        assert_eq!(
+            sanitize_contents("attack.log", "from 1.2.3.4".to_owned()).as_deref(),
+            Some("from -censored-")
+        );
+        assert_eq!(sanitize_contents("secret.log", "secret".to_owned()), None);
+    }
+
+    #[test]
+    fn test_label_ip_censors() {
+        let mut policy = SanitizationPolicy::default();
+        policy.label_ip_censors();
+
+        for (filename, contents, expected) in [
+            (
+                "game.log",
+                "[2024-01-02 03:04:05.678] GAME: from 1.2.3.4\n",
+                "[2024-01-02 03:04:05.678] GAME: from -censored(ip:game)-\n",
+            ),
+            (
+                "runtime.log",
+                "[2024-01-02 03:04:05.678] runtime error: bad connection from 10.20.30.40",
+                "[2024-01-02 03:04:05.678] runtime error: bad connection from -censored(ip:runtime)-",
+            ),
+            (
+                "cargo.html",
+                "<b>from 1.2.3.4</b>",
+                "<b>from -censored(ip:html)-</b>",
+            ),
+            (
+                "attack.log",
+                "from 1.2.3.4",
+                "from -censored(ip:passthrough)-",
+            ),
+        ] {
+            assert_eq!(
+                sanitize_contents_with_policy(filename, contents.to_owned(), &policy).as_deref(),
+                Some(expected)
+            );
+        }
+
+        let condensed = runtimes::condense_runtimes_to_string_with_options(
+            "[2024-01-02 03:04:05.678] runtime error: bad connection from 10.20.30.40\n - proc name: Connect (/client/verb/connect)\n -   usr: null\n -   src: null\n",
+            &runtimes::CondenseOptions {
+                label_ip_censors: true,
+                ..Default::default()
+            },
+        );
+        assert!(condensed.contains("runtime error: bad connection from -censored(ip:runtime)-"));
+    }
+
+    #[test]
+    fn test_extension_fallback() {
+        let mut policy = SanitizationPolicy::default();
+
+        for filename in ["new_thing.log", "new_thing.json", "new_thing.html"] {
+            assert_eq!(
+                policy.sanitization_strategy(&Path::new("round-1").join(filename)),
+                None
+            );
+        }
+
+        policy.extension_fallback = true;
+
+        for (filename, strategy) in [
+            ("new_thing.log", Some(Strategy::PassthroughSanitized)),
+            ("new_thing.json", Some(Strategy::PassthroughSanitized)),
+            ("new_thing.html", Some(Strategy::HtmlLog)),
+            ("new_thing.txt", None),
+            // Known files keep their own strategy
+            ("game.log", Some(Strategy::GameLog)),
+        ] {
+            assert_eq!(
+                policy.sanitization_strategy(&Path::new("round-1").join(filename)),
+                strategy,
+                "{filename}"
+            );
+        }
+
+        assert_eq!(
+            sanitize_contents_with_policy(
+                "new_thing.json",
+                r#"{"ip":"1.2.3.4"}"#.to_owned(),
+                &policy
+            )
+            .as_deref(),
+            Some(r#"{"ip":"-censored-"}"#)
+        );
+
+        policy.apply_strict_mode();
+        assert_eq!(
+            policy.sanitization_strategy(Path::new("round-1/new_thing.log")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_known_filenames() {
+        for filename in known_filenames() {
+            assert!(
+                get_sanitization_strategy(&Path::new("round-1").join(filename)).is_some(),
+                "{filename} is known but has no strategy"
+            );
+        }
+
+        for (filename, strategy) in [
+            ("game.log", Strategy::GameLog),
+            ("runtime.log", Strategy::RuntimeLog),
+            ("cargo.html", Strategy::HtmlLog),
+            ("paper.log", Strategy::PaperLog),
+            ("paper.log.json", Strategy::PaperLogJson),
+            ("telecomms.log", Strategy::CommsLog),
+            ("signal.log.json", Strategy::CommsLogJson),
+            ("round_end_data.json", Strategy::RoundEndDataJson),
+            ("round_end_data.html", Strategy::RoundEndDataHtml),
+            ("attack.log", Strategy::PassthroughSanitized),
+            ("attack.log.json", Strategy::Identity),
+        ] {
+            assert!(known_filenames().contains(&filename));
+            assert_eq!(
+                get_sanitization_strategy(Path::new(filename)),
+                Some(strategy)
+            );
+        }
+
+        assert!(get_sanitization_strategy(Path::new("secret.log")).is_none());
+    }
+
+    #[test]
+    fn test_txt_logs_are_published_as_log() {
+        assert_eq!(
+            get_sanitization_strategy(Path::new("round-1/attack.txt")),
+            Some(Strategy::PassthroughSanitized)
+        );
+        assert_eq!(
+            Strategy::PassthroughSanitized.output_path(Path::new("round-1/attack.txt")),
+            Path::new("round-1/attack.log")
+        );
+        assert_eq!(
+            Strategy::PassthroughSanitized.output_path(Path::new("round-1/attack.log")),
+            Path::new("round-1/attack.log")
+        );
+
+        // Only passed through logs, the rest have their own parsers that don't expect .txt
+        for filename in ["game.txt", "paper.txt", "telecomms.txt", "secret.txt"] {
+            assert_eq!(
+                get_sanitization_strategy(&Path::new("round-1").join(filename)),
+                None,
+                "{filename}"
+            );
+        }
+
+        assert_eq!(
+            Strategy::Identity.output_path(Path::new("round-1/perf-1.txt")),
+            Path::new("round-1/perf-1.txt")
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_strategies() {
+        let policy = SanitizationPolicy::strict();
+
+        for path in [
+            "round-1/game.log",
+            "round-1/cargo.html",
+            "round-1/paper.log",
+        ] {
+            assert_eq!(
+                policy.sanitization_strategy(Path::new(path)),
+                get_sanitization_strategy(Path::new(path))
+            );
+        }
+
+        for path in ["round-1/perf-2024.csv", "round-1/profiler/anything.json"] {
+            assert!(get_sanitization_strategy(Path::new(path)).is_some());
+            assert!(policy.sanitization_strategy(Path::new(path)).is_none());
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_sanitize_and_hash() {
+        let (sanitized, hash) = sanitize_and_hash(Path::new("fixtures/game-malformed/game.log"))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            sanitized,
+            std::fs::read_to_string("fixtures/game-malformed/game.log.expected").unwrap()
+        );
+
+        let (_, same_hash) = sanitize_and_hash(Path::new("fixtures/game-malformed/game.log"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(hash, same_hash);
+
+        let (_, other_hash) = sanitize_and_hash(Path::new("fixtures/game-categories/game.log"))
+            .unwrap()
+            .unwrap();
+        assert_ne!(hash, other_hash);
+
+        assert!(
+            sanitize_and_hash(Path::new("fixtures/game-malformed/game.log.expected"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_async_reads() {
+        for path in [
+            "fixtures/game-malformed/game.log",
+            "fixtures/game-malformed/game.log.expected",
+        ] {
+            let path = Path::new(path);
+            assert_eq!(
+                sanitize_and_hash_async(path).await.unwrap(),
+                sanitize_and_hash(path).unwrap()
+            );
+        }
+
+        let path = Path::new("fixtures/game-categories/game.log");
+        assert_eq!(
+            read_to_string_async(path, Some(4096)).await.unwrap(),
+            read_to_string(path, Some(4096)).unwrap()
+        );
+        assert_eq!(
+            read_to_string_async(path, Some(100))
+                .await
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::FileTooLarge
+        );
+    }
+
+    #[test]
+    fn test_resanitize_is_idempotent() {
+        let policy = SanitizationPolicy {
+            game: game::GameLogPolicy {
+                url_filtering: url_filtering::UrlFiltering::StripQuery,
+                ..Default::default()
+            },
+            runtime: runtimes::RuntimeLogPolicy {
+                string_output: runtimes::StringOutputCensoring::QuotedOnly,
+                ..Default::default()
+            },
+            passthrough: PassthroughPolicy {
+                mask_coordinates_in: HashSet::from(["silo.log".to_owned()]),
+                keyword_blocklist: vec!["secret".to_owned()],
+                base64_min_length: Some(32),
+                label_ip_censors: true,
+            },
+            ..Default::default()
+        };
+
+        let mut inputs = vec![
+            (
+                PathBuf::from("round-1/cargo.html"),
+                "<b title=\"1.2.3.4\">a secret from 1.2.3.4</b>".to_owned(),
+            ),
+            (
+                PathBuf::from("round-1/silo.log"),
+                "took iron at (1, 2, 3) eyJuYW1lIjoiU29tZSBCb2R5IiwiaXAiOiIxLjIuMy40In0="
+                    .to_owned(),
+            ),
+        ];
+
+        for round_entry in std::fs::read_dir(FIXTURES_PATH).unwrap() {
+            for file_entry in std::fs::read_dir(round_entry.unwrap().path()).unwrap() {
+                let path = file_entry.unwrap().path();
+                if get_sanitization_strategy(&path).is_some() {
+                    let contents = std::fs::read_to_string(&path).unwrap();
+                    inputs.push((path, contents));
+                }
+            }
+        }
+
+        // Named *.game.log, so they need to be passed as game.log to get the right strategy
+        for entry in std::fs::read_dir("fixtures/game-snapshots").unwrap() {
+            let path = entry.unwrap().path();
+            inputs.push((
+                path.with_file_name("game.log"),
+                std::fs::read_to_string(&path).unwrap(),
+            ));
+        }
+
+        for (path, contents) in inputs {
+            let strategy = get_sanitization_strategy(&path).unwrap();
+
+            let sanitized = strategy.sanitize(&path, contents, &policy);
+            assert_eq!(
+                strategy.resanitize(&path, sanitized.clone(), &policy),
+                sanitized,
+                "{}",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixtures() {
+        let mut checked = 0;
+
+        for round_entry in std::fs::read_dir(FIXTURES_PATH).unwrap() {
+            let round_entry = round_entry.unwrap();
+
+            for file_entry in std::fs::read_dir(round_entry.path()).unwrap() {
+                let path = file_entry.unwrap().path();
+
+                let Some(strategy) = get_file_sanitization_strategy(&path) else {
+                    continue;
+                };
+
+                let filename = path.file_name().unwrap().to_string_lossy();
+                let contents = std::fs::read_to_string(&path).unwrap();
+
+                assert_matches_fixture(
+                    &expected_path(&path, &filename),
+                    &strategy(contents.clone()),
+                );
+                checked += 1;
+
+                if filename == "runtime.log" {
+                    assert_matches_fixture(
+                        &expected_path(&path, "runtime.condensed.txt"),
+                        &runtimes::condense_runtimes_to_string(&contents),
+                    );
+
+                    assert_matches_fixture(
+                        &expected_path(&path, "runtime.condensed.json"),
+                        &format!("{:#}\n", runtimes::condense_runtimes_to_json(&contents)),
+                    );
+                }
+            }
+        }
+
+        assert!(checked > 0, "no fixtures were found");
+    }
+
+    #[test]
+    fn test_fixture_outputs_have_no_ips() {
+        for round_entry in std::fs::read_dir(FIXTURES_PATH).unwrap() {
+            for file_entry in std::fs::read_dir(round_entry.unwrap().path()).unwrap() {
+                let path = file_entry.unwrap().path();
+                if path.extension().and_then(OsStr::to_str) != Some("expected") {
+                    continue;
+                }
+
+                let contents = std::fs::read_to_string(&path).unwrap();
+                for (index, line) in contents.lines().enumerate() {
+                    assert!(
+                        !ip_filtering::contains_ip(line),
+                        "{}:{} has an IP: {line}",
+                        path.display(),
+                        index + 1
+                    );
+                }
+            }
+        }
+    }
 }
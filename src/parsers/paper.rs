@@ -0,0 +1,145 @@
+// paper.log is whatever players wrote on paper, which can be anything at all. Operators can have the
+// writing censored while keeping who wrote it and where. The writing is taken to be everything from the
+// first quote on, like: [timestamp] PAPER: Somebody/(Some Body) wrote on "paper" at Bridge (1,2,3): "hello"
+// Paper names are player written too, so they go with it. Writing with newlines in it continues onto
+// lines without a timestamp, which are censored whole.
+use std::borrow::Cow;
+
+use super::{
+    ip_filtering::filter_ips,
+    player_content::{censor_json_lines, censor_lines, filter_uncensored},
+};
+
+const CENSORED_CONTENT: &str = "-censored(paper content)-";
+
+#[derive(Clone, Debug, Default)]
+pub struct PaperLogPolicy {
+    // Without this, paper logs are passed through like any other log, with IPs and keywords filtered
+    pub censor_content: bool,
+}
+
+pub fn process_paper_log(contents: String) -> String {
+    filter_ips(&contents).into_owned()
+}
+
+pub fn process_paper_log_with_policy(
+    contents: &str,
+    policy: &PaperLogPolicy,
+    keyword_blocklist: &[String],
+) -> String {
+    if !policy.censor_content {
+        return filter_uncensored(contents, keyword_blocklist);
+    }
+
+    censor_lines(
+        contents,
+        keyword_blocklist,
+        CENSORED_CONTENT,
+        censor_message_content,
+    )
+}
+
+// paper.log.json has one JSON object per line, with the writing in "message" and "data"
+pub fn process_paper_log_json_with_policy(
+    contents: &str,
+    policy: &PaperLogPolicy,
+    keyword_blocklist: &[String],
+) -> String {
+    if !policy.censor_content {
+        return filter_uncensored(contents, keyword_blocklist);
+    }
+
+    censor_json_lines(
+        contents,
+        keyword_blocklist,
+        censor_message_content,
+        censor_strings,
+    )
+}
+
+fn censor_message_content(message: &str) -> Cow<'_, str> {
+    match message.find('"') {
+        Some(content_start) => {
+            Cow::Owned(format!("{}{CENSORED_CONTENT}", &message[..content_start]))
+        }
+        None => Cow::Borrowed(message),
+    }
+}
+
+fn censor_strings(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => *text = CENSORED_CONTENT.to_owned(),
+        serde_json::Value::Array(values) => values.iter_mut().for_each(censor_strings),
+        serde_json::Value::Object(entries) => entries.values_mut().for_each(censor_strings),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAPER_LOG: &str = "\
+[2024-01-02 03:04:05.678] PAPER: Somebody/(Some Body) from 1.2.3.4 wrote on \"paper\" at Bridge (1,2,3): \"my address is
+123 Fake Street\"
+[2024-01-02 03:04:06.000] PAPER: Somebody/(Some Body) crumpled up some paper
+";
+
+    #[test]
+    fn test_passthrough_by_default() {
+        assert_eq!(
+            process_paper_log_with_policy(PAPER_LOG, &PaperLogPolicy::default(), &[]),
+            PAPER_LOG.replace("1.2.3.4", "-censored-")
+        );
+
+        assert_eq!(
+            process_paper_log_with_policy(
+                PAPER_LOG,
+                &PaperLogPolicy::default(),
+                &["fake street".to_owned()]
+            ),
+            PAPER_LOG
+                .replace("1.2.3.4", "-censored-")
+                .replace("Fake Street", "-censored(keyword)-")
+        );
+    }
+
+    #[test]
+    fn test_censor_content() {
+        let policy = PaperLogPolicy {
+            censor_content: true,
+        };
+
+        assert_eq!(
+            process_paper_log_with_policy(PAPER_LOG, &policy, &["somebody".to_owned()]),
+            "\
+[2024-01-02 03:04:05.678] PAPER: -censored(keyword)-/(Some Body) from -censored- wrote on -censored(paper content)-
+-censored(paper content)-
+[2024-01-02 03:04:06.000] PAPER: -censored(keyword)-/(Some Body) crumpled up some paper
+"
+        );
+    }
+
+    #[test]
+    fn test_censor_json_content() {
+        let policy = PaperLogPolicy {
+            censor_content: true,
+        };
+
+        let contents = r#"{"ts":"2024-01-02 03:04:05.678","category":"PAPER","message":"Somebody wrote on \"paper\"","data":{"text":"hello from 1.2.3.4","length":5}}
+not json
+"#;
+
+        assert_eq!(
+            process_paper_log_json_with_policy(contents, &policy, &[]),
+            r#"{"category":"PAPER","data":{"length":5,"text":"-censored(paper content)-"},"message":"Somebody wrote on -censored(paper content)-","ts":"2024-01-02 03:04:05.678"}
+{"censored":"invalid json"}
+"#
+        );
+
+        assert_eq!(
+            process_paper_log_json_with_policy(contents, &PaperLogPolicy::default(), &[]),
+            contents.replace("1.2.3.4", "-censored-")
+        );
+    }
+}
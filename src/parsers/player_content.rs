@@ -0,0 +1,85 @@
+// Shared by logs where players can write whatever they like, like paper and comms, and operators can have
+// that censored while keeping the rest of the line. Each log decides what its content is, this walks the lines
+// and filters IPs and keywords out of everything that's left.
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use super::{
+    ip_filtering::filter_ips,
+    keyword_filtering::{filter_keywords, keyword_regex},
+};
+
+// For plain logs. Lines without a timestamp are content that went onto more than one line, so they're censored whole.
+pub(crate) fn censor_lines(
+    contents: &str,
+    keyword_blocklist: &[String],
+    censored: &str,
+    censor_content: impl for<'a> Fn(&'a str) -> Cow<'a, str>,
+) -> String {
+    let keyword_regex = keyword_regex(keyword_blocklist);
+
+    let mut output = String::with_capacity(contents.len());
+    for line in contents.lines() {
+        let line = if line.trim().is_empty() {
+            Cow::Borrowed(line)
+        } else if !line.starts_with('[') {
+            Cow::Borrowed(censored)
+        } else {
+            censor_content(line)
+        };
+
+        output.push_str(&filter_metadata(&line, keyword_regex.as_ref()));
+        output.push('\n');
+    }
+
+    output
+}
+
+// For the .log.json variants, which have one JSON object per line, with the line in "message" and its parts in "data"
+pub(crate) fn censor_json_lines(
+    contents: &str,
+    keyword_blocklist: &[String],
+    censor_content: impl for<'a> Fn(&'a str) -> Cow<'a, str>,
+    censor_data: impl Fn(&mut serde_json::Value),
+) -> String {
+    let keyword_regex = keyword_regex(keyword_blocklist);
+
+    let mut output = String::with_capacity(contents.len());
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            output.push('\n');
+            continue;
+        }
+
+        let json = match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(serde_json::Value::Object(mut entry)) => {
+                if let Some(serde_json::Value::String(message)) = entry.get_mut("message") {
+                    *message = censor_content(message).into_owned();
+                }
+
+                if let Some(data) = entry.get_mut("data") {
+                    censor_data(data);
+                }
+
+                serde_json::Value::Object(entry)
+            }
+
+            _ => serde_json::json!({ "censored": "invalid json" }),
+        };
+
+        output.push_str(&filter_metadata(&json.to_string(), keyword_regex.as_ref()));
+        output.push('\n');
+    }
+
+    output
+}
+
+// For when the content isn't being censored, which still has IPs and keywords filtered out of everything
+pub(crate) fn filter_uncensored(contents: &str, keyword_blocklist: &[String]) -> String {
+    filter_metadata(contents, keyword_regex(keyword_blocklist).as_ref())
+}
+
+fn filter_metadata(text: &str, keyword_regex: Option<&Regex>) -> String {
+    filter_keywords(&filter_ips(text), keyword_regex).into_owned()
+}
@@ -0,0 +1,243 @@
+// round_end_data.json is the round summary the game writes when the round ends. Alongside the summary
+// it can have connection details and whatever else servers have added to it, so keys that are known
+// to be private are censored wherever they show up, and IPs are censored out of every string.
+// Files with nothing to censor are published byte for byte. Otherwise they're written back out compactly,
+// with object keys sorted and floats as serde_json prints them, so don't expect the original formatting.
+// round_end_data.html is the same data as a page, so there the keys are looked for in the text, like "CID: 1234".
+use std::borrow::Cow;
+
+use regex::{Regex, RegexBuilder};
+
+use super::{html::process_html_log_with_text_filter, ip_filtering::filter_ips, PassthroughPolicy};
+
+const CENSORED_VALUE: &str = "-censored(round end data)-";
+
+#[derive(Clone, Debug)]
+pub struct RoundEndDataPolicy {
+    // Keys whose values are censored entirely, wherever they are. Case insensitive.
+    pub sensitive_keys: Vec<String>,
+}
+
+impl Default for RoundEndDataPolicy {
+    fn default() -> Self {
+        Self {
+            sensitive_keys: ["ip", "cid", "computer_id", "address"]
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+}
+
+impl RoundEndDataPolicy {
+    fn is_sensitive(&self, key: &str) -> bool {
+        self.sensitive_keys
+            .iter()
+            .any(|sensitive_key| sensitive_key.eq_ignore_ascii_case(key))
+    }
+}
+
+pub fn process_round_end_data_json(contents: String) -> String {
+    process_round_end_data_json_with_policy(&contents, &RoundEndDataPolicy::default())
+}
+
+pub fn process_round_end_data_json_with_policy(
+    contents: &str,
+    policy: &RoundEndDataPolicy,
+) -> String {
+    if contents.trim().is_empty() {
+        return contents.to_owned();
+    }
+
+    // Anything we can't walk could have anything in it
+    let Ok(mut json) = serde_json::from_str::<serde_json::Value>(contents) else {
+        return serde_json::json!({ "censored": "invalid json" }).to_string();
+    };
+
+    if !censor_value(&mut json, policy) {
+        return contents.to_owned();
+    }
+
+    json.to_string()
+}
+
+// Returns whether anything was censored
+fn censor_value(value: &mut serde_json::Value, policy: &RoundEndDataPolicy) -> bool {
+    match value {
+        serde_json::Value::String(text) => match filter_ips(text) {
+            Cow::Owned(filtered) => {
+                *text = filtered;
+                true
+            }
+            Cow::Borrowed(_) => false,
+        },
+
+        serde_json::Value::Array(values) => values.iter_mut().fold(false, |changed, value| {
+            censor_value(value, policy) | changed
+        }),
+
+        serde_json::Value::Object(entries) => {
+            let mut changed = false;
+
+            for (key, value) in entries.iter_mut() {
+                if policy.is_sensitive(key) {
+                    // Already censored, like when resanitizing
+                    if value.as_str() != Some(CENSORED_VALUE) {
+                        *value = serde_json::Value::String(CENSORED_VALUE.to_owned());
+                        changed = true;
+                    }
+                } else {
+                    changed |= censor_value(value, policy);
+                }
+            }
+
+            changed
+        }
+
+        serde_json::Value::Null | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => {
+            false
+        }
+    }
+}
+
+pub fn process_round_end_data_html(contents: String) -> String {
+    process_round_end_data_html_with_policy(
+        &contents,
+        "round_end_data.html",
+        &RoundEndDataPolicy::default(),
+        &PassthroughPolicy::default(),
+    )
+}
+
+// Sanitized like any other HTML log, then with "key: value" censored in the text for every sensitive key
+pub fn process_round_end_data_html_with_policy(
+    contents: &str,
+    filename: &str,
+    policy: &RoundEndDataPolicy,
+    passthrough: &PassthroughPolicy,
+) -> String {
+    let sensitive_regex = sensitive_text_regex(&policy.sensitive_keys);
+    let replacement = format!("${{key}}{CENSORED_VALUE}");
+
+    process_html_log_with_text_filter(
+        contents,
+        filename,
+        passthrough,
+        |text| match &sensitive_regex {
+            Some(sensitive_regex) => sensitive_regex.replace_all(text, replacement.as_str()),
+            None => Cow::Borrowed(text),
+        },
+    )
+}
+
+fn sensitive_text_regex(sensitive_keys: &[String]) -> Option<Regex> {
+    if sensitive_keys.is_empty() {
+        return None;
+    }
+
+    let alternatives = sensitive_keys
+        .iter()
+        .map(|key| regex::escape(key))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    Some(
+        RegexBuilder::new(&format!(
+            r"(?P<key>\b(?:{alternatives})\s*[:=]\s*)[^\s,;)]+"
+        ))
+        .case_insensitive(true)
+        .build()
+        .expect("escaped keys should always be a valid regex"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUND_END_DATA: &str = r#"{
+        "round_id": 12345,
+        "map": "MetaStation",
+        "duration": "01:23:45",
+        "station_integrity": 87,
+        "players": [
+            {
+                "ckey": "somebody",
+                "name": "Some Body",
+                "job": "Captain",
+                "ip": "1.2.3.4",
+                "cid": "1234567890",
+                "connection": { "address": "5.6.7.8", "byond_version": 515 }
+            }
+        ],
+        "antagonists": [
+            { "name": "Evil Guy", "role": "Traitor", "objectives": ["steal the disk"], "greentext": true }
+        ],
+        "admin_notes": "banned someone from 9.10.11.12"
+    }"#;
+
+    #[test]
+    fn test_round_end_data_json() {
+        let sanitized: serde_json::Value =
+            serde_json::from_str(&process_round_end_data_json(ROUND_END_DATA.to_owned())).unwrap();
+
+        assert_eq!(
+            sanitized,
+            serde_json::json!({
+                "round_id": 12345,
+                "map": "MetaStation",
+                "duration": "01:23:45",
+                "station_integrity": 87,
+                "players": [
+                    {
+                        "ckey": "somebody",
+                        "name": "Some Body",
+                        "job": "Captain",
+                        "ip": "-censored(round end data)-",
+                        "cid": "-censored(round end data)-",
+                        "connection": { "address": "-censored(round end data)-", "byond_version": 515 }
+                    }
+                ],
+                "antagonists": [
+                    { "name": "Evil Guy", "role": "Traitor", "objectives": ["steal the disk"], "greentext": true }
+                ],
+                "admin_notes": "banned someone from -censored-"
+            })
+        );
+
+        let ckeys_too = RoundEndDataPolicy {
+            sensitive_keys: vec!["CKEY".to_owned()],
+        };
+        let sanitized = process_round_end_data_json_with_policy(ROUND_END_DATA, &ckeys_too);
+        assert!(!sanitized.contains("somebody"));
+        assert!(sanitized.contains("1234567890"));
+        assert!(!sanitized.contains("1.2.3.4"));
+
+        assert_eq!(
+            process_round_end_data_json("{\"ip\": \"1.2.3.4\"".to_owned()),
+            r#"{"censored":"invalid json"}"#
+        );
+        assert_eq!(process_round_end_data_json("".to_owned()), "");
+    }
+
+    #[test]
+    fn test_round_end_data_json_unchanged() {
+        let contents =
+            "{\n  \"round_id\": 12345,\n  \"map\": \"MetaStation\",\n  \"threat\": 0.1\n}\n";
+        assert_eq!(process_round_end_data_json(contents.to_owned()), contents);
+
+        let censored = process_round_end_data_json(ROUND_END_DATA.to_owned());
+        assert_eq!(process_round_end_data_json(censored.clone()), censored);
+    }
+
+    #[test]
+    fn test_round_end_data_html() {
+        assert_eq!(
+            process_round_end_data_html(
+                "<li class='cid'>Some Body (CID: 1234567890, IP = 1.2.3.4) was the Captain</li>"
+                    .to_owned()
+            ),
+            "<li class='cid'>Some Body (CID: -censored(round end data)-, IP = -censored(round end data)-) was the Captain</li>"
+        );
+    }
+}
@@ -1,41 +1,338 @@
-use std::{borrow::Cow, collections::HashMap, iter::Peekable, sync::LazyLock};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    iter::Peekable,
+    sync::LazyLock,
+};
+
+use regex::{Captures, Regex};
+
+use crate::parsers::{
+    audit::{AuditRecord, AuditSink},
+    ip_filtering::{filter_ips, filter_ips_in, find_ips, IpCensorContext},
+    strip_bom,
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeLogPolicy {
+    pub string_output: StringOutputCensoring,
+    pub string_output_patterns: StringOutputPatterns,
+
+    // Censor IPs as -censored(ip:runtime)- rather than -censored-, to tell where they were censored
+    pub label_ip_censors: bool,
+}
+
+impl RuntimeLogPolicy {
+    fn ip_censor_context(&self) -> Option<IpCensorContext> {
+        self.label_ip_censors.then_some(IpCensorContext::Runtime)
+    }
+}
 
-use regex::Regex;
+static CANNOT_READ_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"Cannot read ".*$"#).unwrap());
+
+// Lines matching any of these have BYOND printing a string in them, and are censored as string output,
+// however string_output says to. Builds word these differently, and some servers want list and datum dumps
+// censored too, so more can be added. The default is only "Cannot read "...".
+#[derive(Clone, Debug)]
+pub struct StringOutputPatterns(pub Vec<Regex>);
+
+impl Default for StringOutputPatterns {
+    fn default() -> Self {
+        Self(vec![CANNOT_READ_REGEX.clone()])
+    }
+}
+
+impl StringOutputPatterns {
+    pub fn with_pattern(mut self, pattern: Regex) -> Self {
+        self.0.push(pattern);
+        self
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        self.0.iter().any(|pattern| pattern.is_match(line))
+    }
+}
 
-use crate::parsers::ip_filtering::filter_ips;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StringOutputCensoring {
+    // Censor the whole line
+    #[default]
+    WholeLine,
+
+    // Only censor what's between each pair of quotes, keeping the proc context around it.
+    // Player text can have quotes in it too, so lines with an odd number of them are censored whole.
+    QuotedOnly,
+}
 
 pub fn process_runtimes_log(contents: String) -> String {
-    contents
+    process_runtimes_log_with_policy(&contents, &RuntimeLogPolicy::default())
+}
+
+pub fn process_runtimes_log_with_policy(contents: &str, policy: &RuntimeLogPolicy) -> String {
+    filter_ips_in(strip_bom(contents), policy.ip_censor_context())
         .lines()
-        .map(|line| sanitize_runtimes_line(line))
+        .map(|line| sanitize_runtimes_line(line, policy))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Like process_runtimes_log, but reports every censored line to the sink
+pub fn process_runtimes_log_audited(
+    contents: &str,
+    policy: &RuntimeLogPolicy,
+    file: &str,
+    sink: &mut dyn AuditSink,
+) -> String {
+    strip_bom(contents)
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            for ip in find_ips(line) {
+                sink.record(AuditRecord {
+                    file,
+                    line: index + 1,
+                    reason: "ip",
+                    original: ip,
+                });
+            }
+
+            let filtered = filter_ips_in(line, policy.ip_censor_context());
+            let sanitized = sanitize_runtimes_line(&filtered, policy);
+
+            if let Cow::Owned(_) = sanitized {
+                sink.record(AuditRecord {
+                    file,
+                    line: index + 1,
+                    reason: "string output",
+                    original: line,
+                });
+            }
+
+            sanitized.into_owned()
+        })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
 // Remove BYOND printed strings
-fn sanitize_runtimes_line<'a>(line: &'a str) -> Cow<'a, str> {
-    static STRING_OUTPUT_REGEX: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r#"^.*Cannot read ".*$"#).unwrap());
+fn sanitize_runtimes_line<'a>(line: &'a str, policy: &RuntimeLogPolicy) -> Cow<'a, str> {
+    static QUOTED_STRING_REGEX: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#""[^"]*""#).unwrap());
+
+    if !policy.string_output_patterns.is_match(line) {
+        return Cow::Borrowed(line);
+    }
 
-    STRING_OUTPUT_REGEX.replace(line, "-censored (string output)")
+    if policy.string_output == StringOutputCensoring::QuotedOnly
+        && line.matches('"').count().is_multiple_of(2)
+    {
+        return Cow::Owned(
+            QUOTED_STRING_REGEX
+                .replace_all(line, "\"-censored-\"")
+                .into_owned(),
+        );
+    }
+
+    Cow::Owned("-censored (string output)".to_owned())
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, serde::Serialize)]
 struct CondensedRuntimeKey<'a> {
-    message: &'a str,
-    proc_name: &'a str,
+    // What runtimes are grouped by. Same as the message unless normalize_messages is set,
+    // in which case the message shown is the first one we saw.
+    #[serde(skip)]
+    message_key: Cow<'a, str>,
+    proc_name: Cow<'a, str>,
+    severity: RuntimeSeverity,
+}
+
+impl CondensedRuntimeKey<'_> {
+    fn into_owned(self) -> CondensedRuntimeKey<'static> {
+        CondensedRuntimeKey {
+            message_key: Cow::Owned(self.message_key.into_owned()),
+            proc_name: Cow::Owned(self.proc_name.into_owned()),
+            severity: self.severity,
+        }
+    }
+}
+
+// From the optional prefix before "runtime error:"
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeSeverity {
+    // No prefix, which is most of them
+    #[default]
+    Unmarked,
+    Runtime,
+    Warning,
+}
+
+impl RuntimeSeverity {
+    fn from_prefix(prefix: Option<&str>) -> Self {
+        match prefix {
+            Some("RUNTIME") => RuntimeSeverity::Runtime,
+            Some("WARNING") => RuntimeSeverity::Warning,
+            _ => RuntimeSeverity::Unmarked,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RuntimeSeverity::Unmarked => "unmarked",
+            RuntimeSeverity::Runtime => "runtime",
+            RuntimeSeverity::Warning => "warning",
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize)]
 struct CondensedRuntimeValue<'a> {
-    source_file: Option<&'a str>,
-    usr: &'a str,
-    src: &'a str,
-    src_loc: Option<&'a str>,
+    message: Cow<'a, str>,
+    source_file: Option<Cow<'a, str>>,
+    usr: Cow<'a, str>,
+    src: Cow<'a, str>,
+    src_loc: Option<Cow<'a, str>>,
+
+    // Fields beyond the upstream ones, by logical name, see RuntimeFieldNames
+    #[serde(flatten)]
+    extra_fields: BTreeMap<String, Cow<'a, str>>,
+
+    // The first occurrence's lines as they were, if keep_raw is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw: Option<Cow<'a, str>>,
 
     count: u64,
 }
 
+impl CondensedRuntimeValue<'_> {
+    fn into_owned(self) -> CondensedRuntimeValue<'static> {
+        CondensedRuntimeValue {
+            message: Cow::Owned(self.message.into_owned()),
+            source_file: self
+                .source_file
+                .map(|source_file| Cow::Owned(source_file.into_owned())),
+            usr: Cow::Owned(self.usr.into_owned()),
+            src: Cow::Owned(self.src.into_owned()),
+            src_loc: self.src_loc.map(|src_loc| Cow::Owned(src_loc.into_owned())),
+            extra_fields: self
+                .extra_fields
+                .into_iter()
+                .map(|(name, value)| (name, Cow::Owned(value.into_owned())))
+                .collect(),
+            raw: self.raw.map(|raw| Cow::Owned(raw.into_owned())),
+            count: self.count,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CondenseOptions {
+    // Mask hex refs, numbers, and censored tokens in messages before grouping them,
+    // so runtimes that only differ by those are counted together.
+    pub normalize_messages: bool,
+
+    // Replace the parenthesized ckey in usr and src, for servers that treat ckeys as private
+    pub mask_ckeys: bool,
+
+    // Replace the displayed name before the ckey in usr and src, for servers that treat character names as private.
+    // Fields without a ckey, like "the thing (/datum/thing)", aren't players and are left alone.
+    pub mask_names: bool,
+
+    pub text_format: CondensedTextFormat,
+
+    // Past this many distinct runtimes, any new ones are only counted in a single "(overflow)" entry,
+    // so that a log with millions of unique messages can't use up all our memory
+    pub max_distinct_runtimes: Option<usize>,
+
+    // Keep the whole first occurrence of each runtime in the JSON output as "raw", for bug reports
+    pub keep_raw: bool,
+
+    // Leave out runtimes of any other severity entirely, including from the totals
+    pub only_severities: Option<Vec<RuntimeSeverity>>,
+
+    // Censor IPs as -censored(ip:runtime)- rather than -censored-, to tell where they were censored
+    pub label_ip_censors: bool,
+
+    pub field_names: RuntimeFieldNames,
+
+    // Only affects the human format, the machine format has no blank lines
+    pub spacing: CondensedSpacing,
+}
+
+// Which fields are read after the proc name, as (logical_name, log_key) pairs, in the order they appear in the log.
+// The logical names source_file, usr, src, and src_loc are the condensed output's own fields,
+// and usr and src are required if listed. Any other logical name is kept as an extra field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeFieldNames(pub Vec<(String, String)>);
+
+impl Default for RuntimeFieldNames {
+    fn default() -> Self {
+        Self(
+            [
+                ("source_file", "source file"),
+                ("usr", "usr"),
+                ("src", "src"),
+                ("src_loc", "src.loc"),
+            ]
+            .into_iter()
+            .map(|(logical_name, log_key)| (logical_name.to_owned(), log_key.to_owned()))
+            .collect(),
+        )
+    }
+}
+
+impl CondenseOptions {
+    fn ip_censor_context(&self) -> Option<IpCensorContext> {
+        self.label_ip_censors.then_some(IpCensorContext::Runtime)
+    }
+
+    fn at_capacity<K, V>(&self, condensed_runtimes: &HashMap<K, V>) -> bool {
+        self.max_distinct_runtimes
+            .is_some_and(|max_distinct_runtimes| condensed_runtimes.len() >= max_distinct_runtimes)
+    }
+}
+
+fn overflow_entry() -> (CondensedRuntimeKey<'static>, CondensedRuntimeValue<'static>) {
+    (
+        CondensedRuntimeKey {
+            message_key: Cow::Borrowed("(overflow)"),
+            proc_name: Cow::Borrowed("(overflow)"),
+            severity: RuntimeSeverity::Unmarked,
+        },
+        CondensedRuntimeValue {
+            message: Cow::Borrowed("(overflow)"),
+            source_file: None,
+            usr: Cow::Borrowed(""),
+            src: Cow::Borrowed(""),
+            src_loc: None,
+            extra_fields: BTreeMap::new(),
+            raw: None,
+            count: 0,
+        },
+    )
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CondensedTextFormat {
+    // The prose format that runtime.condensed.txt has always used
+    #[default]
+    Human,
+
+    // Meant to be read back by tools, see condensed_runtimes_to_machine_text
+    Machine,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CondensedSpacing {
+    // A blank line before and after every runtime, and two at the end, the same as the original C++ condenser
+    #[default]
+    Legacy,
+
+    // A single blank line between sections and runtimes, and the output ends with one newline
+    Compact,
+}
+
 #[derive(Debug, serde::Serialize)]
 struct CondensedRuntime<'a> {
     #[serde(flatten)]
@@ -43,13 +340,56 @@ struct CondensedRuntime<'a> {
 
     #[serde(flatten)]
     value: CondensedRuntimeValue<'a>,
+
+    // Of all runtimes, from 0 to 100
+    percentage: f64,
 }
 
 pub fn condense_runtimes_to_string(contents: &str) -> String {
-    let contents = filter_ips(contents);
+    condense_runtimes_to_string_with_options(contents, &CondenseOptions::default())
+}
+
+pub fn condense_runtimes_to_string_with_options(
+    contents: &str,
+    options: &CondenseOptions,
+) -> String {
+    let contents = filter_ips_in(strip_bom(contents), options.ip_censor_context());
+
+    get_condensed_runtimes(&contents, options)
+        .to_text_with_spacing(options.text_format, options.spacing)
+}
 
-    let condensed_runtimes = get_condensed_runtimes(&contents);
+#[derive(serde::Serialize)]
+pub struct CondensedRuntimes<'a> {
+    total_count: u64,
+    runtimes: Vec<CondensedRuntime<'a>>,
+}
+
+impl CondensedRuntimes<'_> {
+    pub fn to_text(&self, text_format: CondensedTextFormat) -> String {
+        self.to_text_with_spacing(text_format, CondensedSpacing::default())
+    }
+
+    pub fn to_text_with_spacing(
+        &self,
+        text_format: CondensedTextFormat,
+        spacing: CondensedSpacing,
+    ) -> String {
+        match text_format {
+            CondensedTextFormat::Human => condensed_runtimes_to_human_text(self, spacing),
+            CondensedTextFormat::Machine => condensed_runtimes_to_machine_text(self),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("couldn't serialize json")
+    }
+}
 
+fn condensed_runtimes_to_human_text(
+    condensed_runtimes: &CondensedRuntimes,
+    spacing: CondensedSpacing,
+) -> String {
     let mut lines = vec![
 		"Note: The source file, src and usr are all from the FIRST of the identical runtimes. Everything else is cropped.".to_owned(),
 		"".to_owned(),
@@ -59,58 +399,334 @@ pub fn condense_runtimes_to_string(contents: &str) -> String {
 		"** Runtimes **".to_owned(),
 	];
 
-    for runtime in condensed_runtimes.runtimes {
-        lines.push("".to_owned());
+    for runtime in &condensed_runtimes.runtimes {
+        if spacing == CondensedSpacing::Legacy || lines.last().is_some_and(|line| !line.is_empty())
+        {
+            lines.push("".to_owned());
+        }
 
         lines.push(format!(
-            "The following runtime has occurred {} time(s).",
-            runtime.value.count
+            "The following runtime has occurred {} time(s) ({:.1}% of all runtimes).",
+            runtime.value.count, runtime.percentage
         ));
 
-        lines.push(format!("runtime error: {}", runtime.key.message));
+        lines.push(format!("runtime error: {}", runtime.value.message));
         lines.push(format!("proc name: {}", runtime.key.proc_name));
 
-        if let Some(source_file) = runtime.value.source_file {
+        if let Some(source_file) = &runtime.value.source_file {
             lines.push(format!("  source file: {source_file}"));
         }
 
         lines.push(format!("  usr: {}", runtime.value.usr));
         lines.push(format!("  src: {}", runtime.value.src));
 
-        if let Some(src_loc) = runtime.value.src_loc {
+        if let Some(src_loc) = &runtime.value.src_loc {
             lines.push(format!("  src.loc: {src_loc}"));
         }
 
-        lines.push("".to_owned());
+        for (name, value) in &runtime.value.extra_fields {
+            lines.push(format!("  {name}: {value}"));
+        }
+
+        if spacing == CondensedSpacing::Legacy {
+            lines.push("".to_owned());
+        }
     }
 
-    lines.push("".to_owned());
-    lines.push("".to_owned());
+    match spacing {
+        CondensedSpacing::Legacy => {
+            lines.push("".to_owned());
+            lines.push("".to_owned());
+        }
+
+        CondensedSpacing::Compact => lines.push("".to_owned()),
+    }
 
     lines.join("\n")
 }
 
+// The machine format is line based, with every line being "key: value".
+// Values never contain newlines, and keys never contain ": ".
+//
+//   output  = header runtime*
+//   header  = "total_unique: " count "\n" "total: " count "\n"
+//   runtime = "=== RUNTIME " index " ===\n" field*
+//   field   = key ": " value "\n"
+//
+// index starts at 1, and runtimes are sorted by count, highest first, then by message and proc name.
+// Each runtime always has count, message, proc_name, usr, and src fields, in that order,
+// followed by source_file and src_loc if they were present, then any extra fields by name,
+// then severity if the runtime had a RUNTIME: or WARNING: prefix.
+fn condensed_runtimes_to_machine_text(condensed_runtimes: &CondensedRuntimes) -> String {
+    let mut output = format!(
+        "total_unique: {}\ntotal: {}\n",
+        condensed_runtimes.runtimes.len(),
+        condensed_runtimes.total_count
+    );
+
+    for (index, runtime) in condensed_runtimes.runtimes.iter().enumerate() {
+        output.push_str(&format!("=== RUNTIME {} ===\n", index + 1));
+        output.push_str(&format!("count: {}\n", runtime.value.count));
+        output.push_str(&format!("message: {}\n", runtime.value.message));
+        output.push_str(&format!("proc_name: {}\n", runtime.key.proc_name));
+        output.push_str(&format!("usr: {}\n", runtime.value.usr));
+        output.push_str(&format!("src: {}\n", runtime.value.src));
+
+        if let Some(source_file) = &runtime.value.source_file {
+            output.push_str(&format!("source_file: {source_file}\n"));
+        }
+
+        if let Some(src_loc) = &runtime.value.src_loc {
+            output.push_str(&format!("src_loc: {src_loc}\n"));
+        }
+
+        for (name, value) in &runtime.value.extra_fields {
+            output.push_str(&format!("{name}: {value}\n"));
+        }
+
+        if runtime.key.severity != RuntimeSeverity::Unmarked {
+            output.push_str(&format!("severity: {}\n", runtime.key.severity.name()));
+        }
+    }
+
+    output
+}
+
 pub fn condense_runtimes_to_json(contents: &str) -> serde_json::Value {
-    serde_json::to_value(get_condensed_runtimes(&filter_ips(contents)))
-        .expect("couldn't serialize json")
+    condense_runtimes_to_json_with_options(contents, &CondenseOptions::default())
 }
 
-#[derive(serde::Serialize)]
-struct CondensedRuntimes<'a> {
+pub fn condense_runtimes_to_json_with_options(
+    contents: &str,
+    options: &CondenseOptions,
+) -> serde_json::Value {
+    let contents = filter_ips_in(strip_bom(contents), options.ip_censor_context());
+
+    get_condensed_runtimes(&contents, options).to_json()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuntimeCounts {
+    pub total: u64,
+    pub unique: usize,
+}
+
+// The same numbers as the condensed output's header, without sorting or formatting anything
+pub fn runtime_counts(contents: &str) -> RuntimeCounts {
+    let contents = filter_ips(strip_bom(contents));
+
+    let mut condensed_runtimes = HashMap::new();
+    let total = condense_into(
+        &contents,
+        &CondenseOptions::default(),
+        &mut condensed_runtimes,
+    );
+
+    RuntimeCounts {
+        total,
+        unique: condensed_runtimes.len(),
+    }
+}
+
+// Condenses runtime.log as it comes in, rather than needing all of it at once.
+// Each runtime is condensed once the next one starts, or at finish.
+pub struct RuntimeCondenser {
+    options: CondenseOptions,
+
+    // Everything from the start of the last runtime we've seen, which could still have more lines coming
+    pending: String,
+
+    // How much of pending has already been checked for runtime starts, so each line is only checked once
+    scanned: usize,
+
+    condensed_runtimes: HashMap<CondensedRuntimeKey<'static>, CondensedRuntimeValue<'static>>,
     total_count: u64,
-    runtimes: Vec<CondensedRuntime<'a>>,
 }
 
-fn get_condensed_runtimes<'a>(runtime_contents: &'a str) -> CondensedRuntimes<'a> {
-    let mut lines = runtime_contents.lines().peekable();
-    let mut condensed_runtimes: HashMap<CondensedRuntimeKey, CondensedRuntimeValue> =
-        HashMap::new();
+impl RuntimeCondenser {
+    pub fn new(options: CondenseOptions) -> Self {
+        Self {
+            options,
+            pending: String::new(),
+            scanned: 0,
+            condensed_runtimes: HashMap::new(),
+            total_count: 0,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        self.pending.push_str(chunk);
+
+        // Only whole lines can be checked for being the start of a runtime
+        let Some(last_newline) = self.pending.rfind('\n') else {
+            return;
+        };
+
+        if last_newline < self.scanned {
+            return;
+        }
+
+        // Anything before scanned isn't a runtime start, other than maybe the very first line
+        let mut last_runtime_start = 0;
+        let mut offset = self.scanned;
+        for line in self.pending[self.scanned..=last_newline].split_inclusive('\n') {
+            if RE_RUNTIME_ERROR_START.is_match(line.trim_end_matches(['\r', '\n'])) {
+                last_runtime_start = offset;
+            }
+
+            offset += line.len();
+        }
+        self.scanned = last_newline + 1;
+
+        if last_runtime_start > 0 {
+            let complete = self.pending.drain(..last_runtime_start).collect::<String>();
+            self.scanned -= last_runtime_start;
+            self.condense(&complete);
+        }
+    }
+
+    pub fn finish(mut self) -> CondensedRuntimes<'static> {
+        let pending = std::mem::take(&mut self.pending);
+        self.condense(&pending);
+
+        sort_condensed_runtimes(self.condensed_runtimes, self.total_count)
+    }
+
+    fn condense(&mut self, contents: &str) {
+        let contents = filter_ips_in(strip_bom(contents), self.options.ip_censor_context());
+
+        let mut condensed_runtimes = HashMap::new();
+        self.total_count += condense_into(&contents, &self.options, &mut condensed_runtimes);
+
+        for (key, value) in condensed_runtimes {
+            let key = key.into_owned();
+
+            if !self.condensed_runtimes.contains_key(&key)
+                && self.options.at_capacity(&self.condensed_runtimes)
+            {
+                let (overflow_key, overflow_value) = overflow_entry();
+                self.condensed_runtimes
+                    .entry(overflow_key)
+                    .or_insert(overflow_value)
+                    .count += value.count;
+                continue;
+            }
+
+            self.condensed_runtimes
+                .entry(key)
+                .and_modify(|existing_value| existing_value.count += value.count)
+                .or_insert_with(|| value.into_owned());
+        }
+    }
+}
 
-    static RE_RUNTIME_ERROR_START: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^\[.+?\] (?:RUNTIME: )?runtime error: (.*)$").unwrap());
+fn normalize_message(message: &str) -> Cow<'_, str> {
+    static RE_VOLATILE_TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"0x[0-9a-fA-F]+|-censored(?:\([^)]*\))?-|[0-9]+(?:\.[0-9]+)?").unwrap()
+    });
 
+    RE_VOLATILE_TOKEN.replace_all(message, "#")
+}
+
+// Splits usr and src like "Some Body (somebody) (/mob/living/carbon/human)" into the name and ckey
+fn mask_player(field: &str, mask_names: bool, mask_ckeys: bool) -> Cow<'_, str> {
+    static RE_PLAYER: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^(?P<name>.+?) \((?P<ckey>[a-z0-9]+)\)").unwrap());
+
+    if !mask_names && !mask_ckeys {
+        return Cow::Borrowed(field);
+    }
+
+    RE_PLAYER.replace(field, |captures: &Captures| {
+        format!(
+            "{} ({})",
+            if mask_names {
+                "-censored(name)-"
+            } else {
+                &captures["name"]
+            },
+            if mask_ckeys {
+                "-censored(ckey)-"
+            } else {
+                &captures["ckey"]
+            },
+        )
+    })
+}
+
+const UNKNOWN_PROC_NAME: &str = "(unknown)";
+
+// Between the lines of a multi-line message, since neither output format can have newlines in it
+const MESSAGE_LINE_SEPARATOR: &str = " | ";
+
+// Multi-line runtimes are only grouped by their first line, since the rest is usually a printout
+// of whatever state things were in, but the message shown is all of the first one's
+fn join_message_lines<'a>(first_line: &'a str, message_lines: &[&str]) -> Cow<'a, str> {
+    if message_lines.is_empty() {
+        return Cow::Borrowed(first_line);
+    }
+
+    let mut message = first_line.to_owned();
+    for message_line in message_lines {
+        message.push_str(MESSAGE_LINE_SEPARATOR);
+        message.push_str(message_line);
+    }
+
+    Cow::Owned(message)
+}
+
+static RE_RUNTIME_ERROR_START: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[.+?\] (?:(RUNTIME|WARNING): )?runtime error: (.*)$").unwrap());
+
+fn get_condensed_runtimes<'a>(
+    runtime_contents: &'a str,
+    options: &CondenseOptions,
+) -> CondensedRuntimes<'a> {
+    let mut condensed_runtimes = HashMap::new();
+    let runtime_count = condense_into(runtime_contents, options, &mut condensed_runtimes);
+
+    sort_condensed_runtimes(condensed_runtimes, runtime_count)
+}
+
+fn sort_condensed_runtimes<'a>(
+    condensed_runtimes: HashMap<CondensedRuntimeKey<'a>, CondensedRuntimeValue<'a>>,
+    total_count: u64,
+) -> CondensedRuntimes<'a> {
+    let mut condensed_runtimes_sorted: Vec<CondensedRuntime> = condensed_runtimes
+        .into_iter()
+        .map(|(key, value)| CondensedRuntime {
+            percentage: value.count as f64 * 100.0 / total_count as f64,
+            key,
+            value,
+        })
+        .collect();
+    // Ties are broken by what the runtimes are grouped by, so the same input always gives the same output
+    condensed_runtimes_sorted.sort_by(|a, b| {
+        b.value
+            .count
+            .cmp(&a.value.count)
+            .then_with(|| a.key.message_key.cmp(&b.key.message_key))
+            .then_with(|| a.key.proc_name.cmp(&b.key.proc_name))
+            .then_with(|| a.key.severity.cmp(&b.key.severity))
+    });
+
+    CondensedRuntimes {
+        total_count,
+        runtimes: condensed_runtimes_sorted,
+    }
+}
+
+// Returns how many runtimes there were
+fn condense_into<'a>(
+    runtime_contents: &'a str,
+    options: &CondenseOptions,
+    condensed_runtimes: &mut HashMap<CondensedRuntimeKey<'a>, CondensedRuntimeValue<'a>>,
+) -> u64 {
+    let mut lines = runtime_contents.lines().peekable();
+
+    // Forks indent these differently, so any amount of spaces or tabs is fine
     static RE_RUNTIME_PROC_NAME: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^ \- (?:proc|verb) name: (.+)$").unwrap());
+        LazyLock::new(|| Regex::new(r"^[ \t]*-[ \t]+(?:proc|verb) name: (.+)$").unwrap());
 
     let mut runtime_count = 0;
 
@@ -119,26 +735,50 @@ fn get_condensed_runtimes<'a>(runtime_contents: &'a str) -> CondensedRuntimes<'a
             continue;
         };
 
-        let runtime = runtime_error_start.get(1).unwrap();
+        let severity =
+            RuntimeSeverity::from_prefix(runtime_error_start.get(1).map(|prefix| prefix.as_str()));
+        if options
+            .only_severities
+            .as_ref()
+            .is_some_and(|only_severities| !only_severities.contains(&severity))
+        {
+            continue;
+        }
+
+        let runtime = runtime_error_start.get(2).unwrap();
         runtime_count += 1;
 
-        // Some runtimes are multi-line
+        // Some runtimes are multi-line, like stack traces with a printout after the message.
+        // Ones that never get a proc name, like the last one in a log that was cut off, are still counted, just without one.
+        let mut message_lines = Vec::new();
         let proc_name = loop {
-            match lines.next() {
-                Some(next_line) => match RE_RUNTIME_PROC_NAME.captures(next_line) {
-                    Some(proc_name) => break proc_name,
-                    None => continue,
-                },
-
-                None => {
-                    break 'main_loop;
-                }
+            let Some(&next_line) = lines.peek() else {
+                break None;
+            };
+
+            if RE_RUNTIME_ERROR_START.is_match(next_line) {
+                break None;
+            }
+
+            lines.next();
+            if let Some(proc_name) = RE_RUNTIME_PROC_NAME.captures(next_line) {
+                break Some(proc_name.get(1).unwrap().as_str());
+            }
+
+            let message_line = next_line.trim();
+            if !message_line.is_empty() {
+                message_lines.push(message_line);
             }
         };
 
         let condensed_runtime_key = CondensedRuntimeKey {
-            message: runtime.as_str(),
-            proc_name: proc_name.get(1).unwrap().as_str(),
+            message_key: if options.normalize_messages {
+                normalize_message(runtime.as_str())
+            } else {
+                Cow::Borrowed(runtime.as_str())
+            },
+            proc_name: Cow::Borrowed(proc_name.unwrap_or(UNKNOWN_PROC_NAME)),
+            severity,
         };
 
         if let Some(condensed_runtime_value) = condensed_runtimes.get_mut(&condensed_runtime_key) {
@@ -146,51 +786,94 @@ fn get_condensed_runtimes<'a>(runtime_contents: &'a str) -> CondensedRuntimes<'a
             continue;
         }
 
-        let source_file = read_field(&mut lines, "source file");
-
-        let Some(usr) = read_field(&mut lines, "usr") else {
-            tracing::error!("next line was not usr");
+        if options.at_capacity(condensed_runtimes) {
+            let (overflow_key, overflow_value) = overflow_entry();
+            condensed_runtimes
+                .entry(overflow_key)
+                .or_insert(overflow_value)
+                .count += 1;
             continue;
-        };
+        }
 
-        let Some(src) = read_field(&mut lines, "src") else {
-            tracing::error!("next line was not src");
-            continue;
+        let mut source_file = None;
+        let mut usr = "";
+        let mut src = "";
+        let mut src_loc = None;
+        let mut extra_fields = BTreeMap::new();
+
+        // The fields come after the proc name, so there aren't any without one
+        let field_names = match proc_name {
+            Some(_) => &options.field_names.0[..],
+            None => &[],
         };
 
-        // It's okay to not have this despite moving the line, since we're not going to get a relevant one anyway
-        let src_loc = read_field(&mut lines, "src.loc");
+        // It's okay to not have the optional ones despite moving the line, since we're not going to get a relevant one anyway
+        for (logical_name, log_key) in field_names {
+            // A log that ends partway through a runtime still has it, with whatever fields made it in.
+            // Only a line that's there and isn't the field is a reason to give up on it.
+            if lines.peek().is_none() {
+                break;
+            }
+
+            let value = read_field(&mut lines, log_key);
+
+            match (logical_name.as_str(), value) {
+                ("usr", Some(value)) => usr = value,
+                ("src", Some(value)) => src = value,
+                ("usr" | "src", None) => {
+                    tracing::error!("next line was not {log_key}");
+                    continue 'main_loop;
+                }
+                ("source_file", value) => source_file = value,
+                ("src_loc", value) => src_loc = value,
+                (_, Some(value)) => {
+                    extra_fields.insert(logical_name.clone(), Cow::Borrowed(value));
+                }
+                (_, None) => {}
+            }
+        }
+
+        let usr = mask_player(usr, options.mask_names, options.mask_ckeys);
+        let src = mask_player(src, options.mask_names, options.mask_ckeys);
+
+        let raw = options.keep_raw.then(|| {
+            let start = offset_in(runtime_contents, start_line);
+            let end = lines.peek().map_or(runtime_contents.len(), |next_line| {
+                offset_in(runtime_contents, next_line)
+            });
+
+            Cow::Borrowed(runtime_contents[start..end].trim_end())
+        });
 
         condensed_runtimes.insert(
             condensed_runtime_key,
             CondensedRuntimeValue {
-                source_file,
+                message: join_message_lines(runtime.as_str(), &message_lines),
+                source_file: source_file.map(Cow::Borrowed),
                 usr,
                 src,
-                src_loc,
+                src_loc: src_loc.map(Cow::Borrowed),
+                extra_fields,
+                raw,
                 count: 1,
             },
         );
     }
 
-    let mut condensed_runtimes_sorted: Vec<CondensedRuntime> = condensed_runtimes
-        .into_iter()
-        .map(|(key, value)| CondensedRuntime { key, value })
-        .collect();
-    condensed_runtimes_sorted.sort_by_key(|runtime| u64::MAX - runtime.value.count);
+    runtime_count
+}
 
-    CondensedRuntimes {
-        total_count: runtime_count,
-        runtimes: condensed_runtimes_sorted,
-    }
+// Where a line from contents.lines() starts in contents
+fn offset_in(contents: &str, line: &str) -> usize {
+    line.as_ptr() as usize - contents.as_ptr() as usize
 }
 
 fn read_field<'a>(
     peekable_lines: &mut Peekable<impl Iterator<Item = &'a str>>,
-    expecting: &'static str,
+    expecting: &str,
 ) -> Option<&'a str> {
     static RE_RUNTIME_FIELD: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^ \-   (.+?): (.+)$").unwrap());
+        LazyLock::new(|| Regex::new(r"^[ \t]*-[ \t]+(.+?): (.+)$").unwrap());
 
     let line = peekable_lines.peek()?;
 
@@ -216,11 +899,12 @@ mod tests {
             return true;
         }
 
-        // This runtime has multiple lines, which C++ can't handle
+        // This runtime has multiple lines, which C++ can't handle, see fixtures/runtime-signal-override for what we keep
         if rust_section.contains("Use override = TRUE to suppress this warning.") {
             return cpp_section.contains("Use override = TRUE to suppress this warning.");
         }
 
+        // C++ only keeps the first line of this one, see fixtures/runtime-overlays for what we keep
         if rust_section.contains("refusing to update and cutting.") {
             return cpp_section.contains("refusing to update and cutting.");
         }
@@ -243,6 +927,9 @@ mod tests {
             return;
         }
 
+        // C++ doesn't show percentages
+        let percentage_regex = Regex::new(r" \([0-9.]+% of all runtimes\)").unwrap();
+
         for day_folder_entry in std::fs::read_dir(log_directory).unwrap() {
             let day_folder_entry = day_folder_entry.unwrap();
 
@@ -269,7 +956,9 @@ mod tests {
                 )
                 .unwrap();
 
-                let condensed_runtimes = condense_runtimes_to_string(&raw_runtimes);
+                let condensed_runtimes = percentage_regex
+                    .replace_all(&condense_runtimes_to_string(&raw_runtimes), "")
+                    .into_owned();
 
                 // The C++ runtime condenser only sorts by count, which means everything else is unspecified.
                 let mut rust_split = condensed_runtimes
@@ -307,6 +996,707 @@ mod tests {
         }
     }
 
+    const NEAR_DUPLICATE_RUNTIMES: &str = "\
+[2024-01-01 00:00:01.000] runtime error: Cannot read null.name [0x3001234]
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,10
+ -   usr: null
+ -   src: /datum (/datum)
+[2024-01-01 00:00:02.000] runtime error: Cannot read null.name [0x3005678]
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,10
+ -   usr: null
+ -   src: /datum (/datum)
+[2024-01-01 00:00:03.000] runtime error: list index out of bounds: 12
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,20
+ -   usr: null
+ -   src: /datum (/datum)
+";
+
+    #[test]
+    fn test_truncated_last_runtime() {
+        let full = "\
+[2024-01-01 00:00:01.000] runtime error: Cannot read null.name
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,10
+ -   usr: Somebody (somebody) (/mob/living/carbon/human)
+ -   src: /datum (/datum)
+ -   src.loc: the floor (1,2,3) (/turf/open/floor)";
+
+        let lines = full.lines().collect::<Vec<_>>();
+
+        // Ending after every line, with and without the last newline
+        for end in 1..=lines.len() {
+            let truncated = lines[..end].join("\n");
+
+            for contents in [truncated.clone(), format!("{truncated}\n")] {
+                let condensed = get_condensed_runtimes(&contents, &CondenseOptions::default());
+                assert_eq!(condensed.total_count, 1, "{contents}");
+                assert_eq!(condensed.runtimes.len(), 1, "{contents}");
+
+                let runtime = &condensed.runtimes[0];
+                assert_eq!(runtime.value.message, "Cannot read null.name");
+                assert_eq!(
+                    runtime.key.proc_name,
+                    if end >= 2 {
+                        "do thing (/datum/proc/do_thing)"
+                    } else {
+                        UNKNOWN_PROC_NAME
+                    }
+                );
+                assert_eq!(
+                    runtime.value.source_file.as_deref(),
+                    (end >= 3).then_some("code/datum.dm,10")
+                );
+                assert_eq!(runtime.value.usr.is_empty(), end < 4, "{contents}");
+                assert_eq!(runtime.value.src.is_empty(), end < 5, "{contents}");
+                assert_eq!(
+                    runtime.value.src_loc.as_deref(),
+                    (end >= 6).then_some("the floor (1,2,3) (/turf/open/floor)")
+                );
+            }
+        }
+
+        // A field that's there but isn't the one expected still means the runtime isn't what we think it is
+        let mismatched = format!(
+            "{}\n -   src.loc: somewhere (1,2,3)\n",
+            lines[..3].join("\n")
+        );
+        let condensed = get_condensed_runtimes(&mismatched, &CondenseOptions::default());
+        assert_eq!(condensed.total_count, 1);
+        assert!(condensed.runtimes.is_empty());
+    }
+
+    #[test]
+    fn test_normalize_messages() {
+        let condensed =
+            get_condensed_runtimes(NEAR_DUPLICATE_RUNTIMES, &CondenseOptions::default());
+        assert_eq!(condensed.total_count, 3);
+        assert_eq!(condensed.runtimes.len(), 3);
+
+        let condensed = get_condensed_runtimes(
+            NEAR_DUPLICATE_RUNTIMES,
+            &CondenseOptions {
+                normalize_messages: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(condensed.total_count, 3);
+        assert_eq!(condensed.runtimes.len(), 2);
+
+        let merged = &condensed.runtimes[0];
+        assert_eq!(merged.value.count, 2);
+        assert_eq!(merged.value.message, "Cannot read null.name [0x3001234]");
+    }
+
+    #[test]
+    fn test_ties_are_deterministic() {
+        let mut runtimes = String::new();
+        for index in 0..20 {
+            runtimes.push_str(&format!(
+                "[2024-01-01 00:00:01.000] runtime error: Cannot read null.thing_{index}\n - proc name: do thing (/datum/proc/do_thing)\n -   usr: null\n -   src: /datum (/datum)\n"
+            ));
+        }
+
+        let first = condense_runtimes_to_json(&runtimes).to_string();
+        for _ in 0..10 {
+            assert_eq!(condense_runtimes_to_json(&runtimes).to_string(), first);
+        }
+
+        let condensed = get_condensed_runtimes(&runtimes, &CondenseOptions::default());
+        assert_eq!(
+            condensed.runtimes[0].value.message,
+            "Cannot read null.thing_0"
+        );
+        assert_eq!(
+            condensed.runtimes[1].value.message,
+            "Cannot read null.thing_1"
+        );
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        let runtimes = format!("\u{feff}{NEAR_DUPLICATE_RUNTIMES}");
+
+        assert_eq!(runtime_counts(&runtimes).total, 3);
+        assert_eq!(
+            process_runtimes_log(runtimes),
+            NEAR_DUPLICATE_RUNTIMES.trim_end()
+        );
+    }
+
+    #[test]
+    fn test_severity() {
+        let runtimes = "\
+[2024-01-01 00:00:01.000] runtime error: Cannot read null.name
+ - proc name: do thing (/datum/proc/do_thing)
+ -   usr: null
+ -   src: /datum (/datum)
+[2024-01-01 00:00:02.000] RUNTIME: runtime error: Cannot read null.name
+ - proc name: do thing (/datum/proc/do_thing)
+ -   usr: null
+ -   src: /datum (/datum)
+[2024-01-01 00:00:03.000] WARNING: runtime error: Cannot read null.name
+ - proc name: do thing (/datum/proc/do_thing)
+ -   usr: null
+ -   src: /datum (/datum)
+";
+
+        let condensed = get_condensed_runtimes(runtimes, &CondenseOptions::default());
+        assert_eq!(condensed.total_count, 3);
+
+        let mut severities = condensed
+            .runtimes
+            .iter()
+            .map(|runtime| runtime.key.severity)
+            .collect::<Vec<_>>();
+        severities.sort_by_key(|severity| severity.name());
+        assert_eq!(
+            severities,
+            [
+                RuntimeSeverity::Runtime,
+                RuntimeSeverity::Unmarked,
+                RuntimeSeverity::Warning
+            ]
+        );
+
+        let condensed = get_condensed_runtimes(
+            runtimes,
+            &CondenseOptions {
+                only_severities: Some(vec![RuntimeSeverity::Unmarked, RuntimeSeverity::Runtime]),
+                ..Default::default()
+            },
+        );
+        assert_eq!(condensed.total_count, 2);
+        assert!(condensed
+            .runtimes
+            .iter()
+            .all(|runtime| runtime.key.severity != RuntimeSeverity::Warning));
+        assert_eq!(condensed.runtimes[0].value.message, "Cannot read null.name");
+    }
+
+    #[test]
+    fn test_mask_ckeys() {
+        let runtimes = "\
+[2024-01-01 00:00:01.000] runtime error: Cannot read null.name
+ - proc name: do thing (/datum/proc/do_thing)
+ -   usr: Some Body (somebody) (/mob/living/carbon/human)
+ -   src: the thing (/datum/thing)
+";
+
+        let condensed = get_condensed_runtimes(runtimes, &CondenseOptions::default());
+        assert_eq!(
+            condensed.runtimes[0].value.usr,
+            "Some Body (somebody) (/mob/living/carbon/human)"
+        );
+
+        let condensed = get_condensed_runtimes(
+            runtimes,
+            &CondenseOptions {
+                mask_ckeys: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            condensed.runtimes[0].value.usr,
+            "Some Body (-censored(ckey)-) (/mob/living/carbon/human)"
+        );
+        assert_eq!(condensed.runtimes[0].value.src, "the thing (/datum/thing)");
+    }
+
+    #[test]
+    fn test_mask_names_and_ckeys() {
+        let usr = "Some Body (somebody) (/mob/living/carbon/human)";
+
+        for (mask_names, mask_ckeys, expected) in [
+            (false, false, usr),
+            (
+                false,
+                true,
+                "Some Body (-censored(ckey)-) (/mob/living/carbon/human)",
+            ),
+            (
+                true,
+                false,
+                "-censored(name)- (somebody) (/mob/living/carbon/human)",
+            ),
+            (
+                true,
+                true,
+                "-censored(name)- (-censored(ckey)-) (/mob/living/carbon/human)",
+            ),
+        ] {
+            assert_eq!(
+                mask_player(usr, mask_names, mask_ckeys),
+                expected,
+                "mask_names = {mask_names}, mask_ckeys = {mask_ckeys}"
+            );
+
+            // Not a player, so there's nothing to split
+            assert_eq!(
+                mask_player("the thing (/datum/thing)", mask_names, mask_ckeys),
+                "the thing (/datum/thing)"
+            );
+            assert_eq!(mask_player("null", mask_names, mask_ckeys), "null");
+        }
+    }
+
+    #[test]
+    fn test_machine_text_format() {
+        let condensed = condense_runtimes_to_string_with_options(
+            NEAR_DUPLICATE_RUNTIMES,
+            &CondenseOptions {
+                normalize_messages: true,
+                text_format: CondensedTextFormat::Machine,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            condensed,
+            "\
+total_unique: 2
+total: 3
+=== RUNTIME 1 ===
+count: 2
+message: Cannot read null.name [0x3001234]
+proc_name: do thing (/datum/proc/do_thing)
+usr: null
+src: /datum (/datum)
+source_file: code/datum.dm,10
+=== RUNTIME 2 ===
+count: 1
+message: list index out of bounds: 12
+proc_name: do thing (/datum/proc/do_thing)
+usr: null
+src: /datum (/datum)
+source_file: code/datum.dm,20
+"
+        );
+    }
+
+    #[test]
+    fn test_compact_spacing() {
+        let options = CondenseOptions {
+            normalize_messages: true,
+            spacing: CondensedSpacing::Compact,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            condense_runtimes_to_string_with_options(NEAR_DUPLICATE_RUNTIMES, &options),
+            "\
+Note: The source file, src and usr are all from the FIRST of the identical runtimes. Everything else is cropped.
+
+Total unique runtimes: 2
+Total runtimes: 3
+
+** Runtimes **
+
+The following runtime has occurred 2 time(s) (66.7% of all runtimes).
+runtime error: Cannot read null.name [0x3001234]
+proc name: do thing (/datum/proc/do_thing)
+  source file: code/datum.dm,10
+  usr: null
+  src: /datum (/datum)
+
+The following runtime has occurred 1 time(s) (33.3% of all runtimes).
+runtime error: list index out of bounds: 12
+proc name: do thing (/datum/proc/do_thing)
+  source file: code/datum.dm,20
+  usr: null
+  src: /datum (/datum)
+"
+        );
+
+        let legacy = condense_runtimes_to_string_with_options(
+            NEAR_DUPLICATE_RUNTIMES,
+            &CondenseOptions {
+                normalize_messages: true,
+                ..Default::default()
+            },
+        );
+        assert!(legacy.ends_with("src: /datum (/datum)\n\n\n"));
+        assert!(legacy.contains("** Runtimes **\n\nThe following"));
+        assert!(legacy.contains("(/datum)\n\n\nThe following"));
+    }
+
+    #[test]
+    fn test_quoted_only_string_output() {
+        let policy = RuntimeLogPolicy {
+            string_output: StringOutputCensoring::QuotedOnly,
+            ..Default::default()
+        };
+
+        for (line, expected) in [
+            (
+                "[2024-01-02 03:04:05.678] runtime error: Cannot read \"secret\".len",
+                "[2024-01-02 03:04:05.678] runtime error: Cannot read \"-censored-\".len",
+            ),
+            (
+                "[2024-01-02 03:04:05.678] runtime error: Cannot read \"one\".len of \"two\"",
+                "[2024-01-02 03:04:05.678] runtime error: Cannot read \"-censored-\".len of \"-censored-\"",
+            ),
+            (
+                "[2024-01-02 03:04:05.678] runtime error: Cannot read \"he said \"hi\".len",
+                "-censored (string output)",
+            ),
+            (
+                "[2024-01-02 03:04:05.678] runtime error: list index out of bounds \"untouched\"",
+                "[2024-01-02 03:04:05.678] runtime error: list index out of bounds \"untouched\"",
+            ),
+        ] {
+            assert_eq!(process_runtimes_log_with_policy(line, &policy), expected);
+        }
+
+        assert_eq!(
+            process_runtimes_log(
+                "[2024-01-02 03:04:05.678] runtime error: Cannot read \"one\".len of \"two\""
+                    .to_owned()
+            ),
+            "-censored (string output)"
+        );
+    }
+
+    #[test]
+    fn test_custom_string_output_patterns() {
+        let contents = "\
+[2024-01-02 03:04:05.678] runtime error: Cannot read \"secret\".len
+[2024-01-02 03:04:05.678] runtime error: bad list: /list (\"one\", \"two\")
+[2024-01-02 03:04:05.678] runtime error: list index out of bounds";
+
+        assert_eq!(
+            process_runtimes_log_with_policy(contents, &RuntimeLogPolicy::default()),
+            "\
+-censored (string output)
+[2024-01-02 03:04:05.678] runtime error: bad list: /list (\"one\", \"two\")
+[2024-01-02 03:04:05.678] runtime error: list index out of bounds"
+        );
+
+        let mut policy = RuntimeLogPolicy {
+            string_output_patterns: StringOutputPatterns::default()
+                .with_pattern(Regex::new(r"/list \(").unwrap()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            process_runtimes_log_with_policy(contents, &policy),
+            "\
+-censored (string output)
+-censored (string output)
+[2024-01-02 03:04:05.678] runtime error: list index out of bounds"
+        );
+
+        policy.string_output = StringOutputCensoring::QuotedOnly;
+        assert_eq!(
+            process_runtimes_log_with_policy(contents, &policy),
+            "\
+[2024-01-02 03:04:05.678] runtime error: Cannot read \"-censored-\".len
+[2024-01-02 03:04:05.678] runtime error: bad list: /list (\"-censored-\", \"-censored-\")
+[2024-01-02 03:04:05.678] runtime error: list index out of bounds"
+        );
+
+        // Replacing the default rather than adding to it
+        policy.string_output_patterns =
+            StringOutputPatterns(vec![Regex::new(r"/list \(").unwrap()]);
+        assert!(process_runtimes_log_with_policy(contents, &policy).contains("\"secret\""));
+    }
+
+    #[test]
+    fn test_label_ip_censors() {
+        let contents = "[2024-01-02 03:04:05.678] runtime error: bad connection from 10.20.30.40";
+        let expected =
+            "[2024-01-02 03:04:05.678] runtime error: bad connection from -censored(ip:runtime)-";
+        let policy = RuntimeLogPolicy {
+            label_ip_censors: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            process_runtimes_log_with_policy(contents, &policy),
+            expected
+        );
+        assert_eq!(
+            process_runtimes_log_audited(
+                contents,
+                &policy,
+                "runtime.log",
+                &mut |_: AuditRecord| {}
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_process_runtimes_log_audited() {
+        let contents = "\
+[2024-01-02 03:04:05.678] runtime error: Cannot read \"secret\".len
+ - proc name: do thing (/datum/proc/do_thing)";
+
+        let mut records = Vec::new();
+        let output = process_runtimes_log_audited(
+            contents,
+            &RuntimeLogPolicy::default(),
+            "runtime.log",
+            &mut |record: AuditRecord| records.push((record.line, record.original.to_owned())),
+        );
+
+        assert_eq!(output, process_runtimes_log(contents.to_owned()));
+        assert_eq!(
+            records,
+            [(
+                1,
+                "[2024-01-02 03:04:05.678] runtime error: Cannot read \"secret\".len".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_max_distinct_runtimes() {
+        let mut contents = String::new();
+        for index in 0..1000 {
+            contents.push_str(&format!(
+                "[2024-01-01 00:00:01.000] runtime error: unique runtime {index}\n - proc name: do thing (/datum/proc/do_thing)\n -   usr: null\n -   src: /datum (/datum)\n"
+            ));
+        }
+
+        let options = CondenseOptions {
+            max_distinct_runtimes: Some(10),
+            ..Default::default()
+        };
+
+        let condensed = get_condensed_runtimes(&contents, &options);
+        assert_eq!(condensed.total_count, 1000);
+        assert_eq!(condensed.runtimes.len(), 11);
+        assert_eq!(condensed.runtimes[0].value.message, "(overflow)");
+        assert_eq!(condensed.runtimes[0].value.count, 990);
+
+        let mut condenser = RuntimeCondenser::new(options);
+        for line in contents.split_inclusive('\n') {
+            condenser.feed(line);
+        }
+
+        let condensed = condenser.finish();
+        assert_eq!(condensed.total_count, 1000);
+        assert_eq!(condensed.runtimes.len(), 11);
+        assert_eq!(condensed.runtimes[0].value.count, 990);
+    }
+
+    #[test]
+    fn test_field_indentation() {
+        // Normalized so that the counts differ, and the order is always the same
+        let options = CondenseOptions {
+            normalize_messages: true,
+            ..Default::default()
+        };
+
+        let expected = condense_runtimes_to_string_with_options(NEAR_DUPLICATE_RUNTIMES, &options);
+        assert!(expected.contains("  source file: code/datum.dm,10"));
+
+        for (from, to) in [(" - ", "\t- "), (" -   ", "\t-\t"), (" -   ", "  -  ")] {
+            let reindented = NEAR_DUPLICATE_RUNTIMES.replace(from, to);
+            assert_eq!(
+                condense_runtimes_to_string_with_options(&reindented, &options),
+                expected,
+                "{from:?} -> {to:?}"
+            );
+        }
+
+        // Still has to look like "- key: value"
+        let no_dash = NEAR_DUPLICATE_RUNTIMES.replace(" -   source file", "    source file");
+        assert!(
+            !condense_runtimes_to_string_with_options(&no_dash, &options)
+                .contains("  source file:")
+        );
+    }
+
+    #[test]
+    fn test_keep_raw() {
+        let contents = "\
+[2024-01-01 00:00:01.000] runtime error: bad thing from 1.2.3.4
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,10
+ -   usr: null
+ -   src: /datum (/datum)
+unrelated line
+[2024-01-01 00:00:02.000] runtime error: bad thing from 1.2.3.4
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,11
+ -   usr: null
+ -   src: /datum (/datum)
+";
+
+        let json = condense_runtimes_to_json_with_options(
+            contents,
+            &CondenseOptions {
+                keep_raw: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            json["runtimes"][0]["raw"],
+            "\
+[2024-01-01 00:00:01.000] runtime error: bad thing from -censored-
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,10
+ -   usr: null
+ -   src: /datum (/datum)"
+        );
+
+        let json = condense_runtimes_to_json(contents);
+        assert!(json["runtimes"][0].get("raw").is_none());
+    }
+
+    #[test]
+    fn test_custom_field_names() {
+        let contents = "\
+[2024-01-01 00:00:01.000] runtime error: bad thing
+ - proc name: do thing (/datum/proc/do_thing)
+ -   source file: code/datum.dm,10
+ -   usr: null
+ -   src: /obj/item (/obj/item)
+ -   location: the floor (1,2,3) (/turf/open/floor)
+ -   turf: the floor (/turf/open/floor)
+";
+
+        let options = CondenseOptions {
+            field_names: RuntimeFieldNames(
+                [
+                    ("source_file", "source file"),
+                    ("usr", "usr"),
+                    ("src", "src"),
+                    ("src_loc", "location"),
+                    ("turf", "turf"),
+                ]
+                .into_iter()
+                .map(|(logical_name, log_key)| (logical_name.to_owned(), log_key.to_owned()))
+                .collect(),
+            ),
+            ..Default::default()
+        };
+
+        let json = condense_runtimes_to_json_with_options(contents, &options);
+        assert_eq!(
+            json["runtimes"][0]["src_loc"],
+            "the floor (1,2,3) (/turf/open/floor)"
+        );
+        assert_eq!(json["runtimes"][0]["turf"], "the floor (/turf/open/floor)");
+
+        let text = condense_runtimes_to_string_with_options(contents, &options);
+        assert!(text.contains("  src.loc: the floor (1,2,3) (/turf/open/floor)\n"));
+        assert!(text.contains("  turf: the floor (/turf/open/floor)\n"));
+
+        // Upstream names don't know about either
+        let json = condense_runtimes_to_json(contents);
+        assert!(json["runtimes"][0]["src_loc"].is_null());
+        assert!(json["runtimes"][0].get("turf").is_none());
+    }
+
+    #[test]
+    fn test_missing_proc_name() {
+        let contents = "\
+[2024-01-01 00:00:01.000] runtime error: cut off
+[2024-01-01 00:00:02.000] runtime error: bad thing
+ - proc name: do thing (/datum/proc/do_thing)
+ -   usr: null
+ -   src: /datum (/datum)
+[2024-01-01 00:00:03.000] runtime error: also cut off
+";
+
+        let json = condense_runtimes_to_json(contents);
+        assert_eq!(json["total_count"], 3);
+
+        let runtimes = json["runtimes"].as_array().unwrap();
+        assert_eq!(runtimes.len(), 3);
+
+        let bad_thing = runtimes
+            .iter()
+            .find(|runtime| runtime["message"] == "bad thing")
+            .unwrap();
+        assert_eq!(bad_thing["proc_name"], "do thing (/datum/proc/do_thing)");
+        assert_eq!(bad_thing["src"], "/datum (/datum)");
+
+        for message in ["cut off", "also cut off"] {
+            let runtime = runtimes
+                .iter()
+                .find(|runtime| runtime["message"] == message)
+                .unwrap();
+            assert_eq!(runtime["proc_name"], "(unknown)");
+        }
+    }
+
+    #[test]
+    fn test_runtime_counts() {
+        assert_eq!(
+            runtime_counts(NEAR_DUPLICATE_RUNTIMES),
+            RuntimeCounts {
+                total: 3,
+                unique: 3
+            }
+        );
+
+        assert_eq!(
+            runtime_counts(""),
+            RuntimeCounts {
+                total: 0,
+                unique: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_runtime_condenser() {
+        let options = CondenseOptions {
+            normalize_messages: true,
+            ..Default::default()
+        };
+
+        let expected = condense_runtimes_to_string_with_options(NEAR_DUPLICATE_RUNTIMES, &options);
+
+        for chunk_size in [1, 2, 7, 64, NEAR_DUPLICATE_RUNTIMES.len()] {
+            let mut condenser = RuntimeCondenser::new(options.clone());
+
+            for chunk in NEAR_DUPLICATE_RUNTIMES.as_bytes().chunks(chunk_size) {
+                condenser.feed(std::str::from_utf8(chunk).unwrap());
+            }
+
+            assert_eq!(
+                condenser.finish().to_text(CondensedTextFormat::Human),
+                expected,
+                "chunk size {chunk_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_runtime_condenser_long_runtime() {
+        let mut runtime = String::from("[2024-01-01 00:00:01.000] runtime error: long printout\n");
+        for index in 0..2000 {
+            runtime.push_str(&format!("  line {index}\n"));
+        }
+        runtime.push_str(
+            " - proc name: do thing (/datum/proc/do_thing)\n -   usr: null\n -   src: null\n",
+        );
+
+        let contents = format!("{runtime}{runtime}");
+        let mut condenser = RuntimeCondenser::new(CondenseOptions::default());
+
+        for chunk in contents.as_bytes().chunks(5) {
+            condenser.feed(std::str::from_utf8(chunk).unwrap());
+
+            // Only the unfinished line at the end is left to check
+            let unscanned = &condenser.pending[condenser.scanned..];
+            assert!(!unscanned.contains('\n'), "{unscanned:?}");
+        }
+
+        assert_eq!(
+            condenser.finish().to_text(CondensedTextFormat::Human),
+            condense_runtimes_to_string(&contents)
+        );
+    }
+
     #[test]
     fn test_2023_11_logs() {
         test_log_directory(
@@ -0,0 +1,99 @@
+use std::{borrow::Cow, sync::LazyLock};
+
+use regex::{Captures, Regex};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UrlFiltering {
+    // Leave URLs untouched
+    #[default]
+    Off,
+
+    // Keep the scheme, host, and path, but drop the query string and fragment
+    StripQuery,
+
+    // Replace the whole URL
+    Censor,
+}
+
+// Only http(s) URLs with an actual host, so things like "byond://" or a bare "://" don't match
+static URL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\bhttps?://[^\s/?#"'<>]+[^\s?#"'<>]*([?#][^\s"'<>]*)?"#).unwrap()
+});
+
+pub fn filter_urls(contents: &str, mode: UrlFiltering) -> Cow<'_, str> {
+    match mode {
+        UrlFiltering::Off => Cow::Borrowed(contents),
+
+        UrlFiltering::StripQuery => URL_REGEX.replace_all(contents, |captures: &Captures| {
+            let url = captures.get(0).unwrap();
+            match captures.get(1) {
+                Some(query) => format!(
+                    "{}-censored(url query)-",
+                    &contents[url.start()..query.start()]
+                ),
+                None => url.as_str().to_owned(),
+            }
+        }),
+
+        UrlFiltering::Censor => URL_REGEX.replace_all(contents, "-censored(url)-"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_query() {
+        assert_eq!(
+            filter_urls(
+                "callback to https://example.com/auth/callback?token=abc123&user=1 failed",
+                UrlFiltering::StripQuery
+            ),
+            "callback to https://example.com/auth/callback-censored(url query)- failed"
+        );
+
+        assert_eq!(
+            filter_urls(
+                "see http://example.com/page#secret",
+                UrlFiltering::StripQuery
+            ),
+            "see http://example.com/page-censored(url query)-"
+        );
+    }
+
+    #[test]
+    fn test_no_query_is_untouched() {
+        let line = "posted https://example.com/some/path and https://tgstation13.org";
+        assert_eq!(filter_urls(line, UrlFiltering::StripQuery), line);
+    }
+
+    #[test]
+    fn test_censor() {
+        assert_eq!(
+            filter_urls(
+                "webhook https://example.com/api/webhooks/123/abc?wait=true and http://example.org",
+                UrlFiltering::Censor
+            ),
+            "webhook -censored(url)- and -censored(url)-"
+        );
+    }
+
+    #[test]
+    fn test_not_over_matched() {
+        for line in [
+            "connected via byond://127.0.0.1:1337?foo=bar",
+            "the sign reads ://?hello",
+            "nothttps://example.com?x=1",
+            "http:// is not a url",
+        ] {
+            assert_eq!(filter_urls(line, UrlFiltering::Censor), line);
+        }
+    }
+
+    #[test]
+    fn test_off() {
+        let line = "https://example.com/?token=abc";
+        assert_eq!(filter_urls(line, UrlFiltering::Off), line);
+    }
+}
@@ -7,7 +7,9 @@ use axum::{
 };
 use serde::Serialize;
 
-use crate::{app_state::AppState, parsers::get_file_sanitization_strategy};
+use tg_public_log_parser::parsers::{self, get_file_sanitization_strategy};
+
+use crate::app_state::AppState;
 
 pub const RUNTIME_CONDENSED_JSON: &str = "runtime.condensed.json";
 pub const RUNTIME_CONDENSED_TXT: &str = "runtime.condensed.txt";
@@ -58,23 +60,25 @@ pub async fn get(
     match requested_path.file_name().and_then(std::ffi::OsStr::to_str) {
         name @ Some(RUNTIME_CONDENSED_TXT) | name @ Some(RUNTIME_CONDENSED_JSON) => {
             let runtimes_file = requested_path.with_file_name("runtime.log");
-            let runtimes_contents = std::fs::read_to_string(runtimes_file).map_err(|error| {
-                error_to_response(error, StatusCode::NOT_FOUND, "couldn't find runtime.log")
-            })?;
+            let runtimes_contents =
+                tokio::fs::read_to_string(runtimes_file)
+                    .await
+                    .map_err(|error| {
+                        error_to_response(error, StatusCode::NOT_FOUND, "couldn't find runtime.log")
+                    })?;
 
             if name == Some(RUNTIME_CONDENSED_TXT) {
                 return Ok((
                     StatusCode::OK,
                     headers("text/plain"),
-                    crate::parsers::runtimes::condense_runtimes_to_string(&runtimes_contents),
+                    parsers::runtimes::condense_runtimes_to_string(&runtimes_contents),
                 )
                     .into_response());
             } else if name == Some(RUNTIME_CONDENSED_JSON) {
                 return Ok((
                     StatusCode::OK,
                     headers("application/json"),
-                    crate::parsers::runtimes::condense_runtimes_to_json(&runtimes_contents)
-                        .to_string(),
+                    parsers::runtimes::condense_runtimes_to_json(&runtimes_contents).to_string(),
                 )
                     .into_response());
             } else {
@@ -146,13 +150,17 @@ pub async fn get(
                     "text/plain"
                 },
             ),
-            strategy(std::fs::read_to_string(&requested_path).map_err(|error| {
-                error_to_response(
-                    error,
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "couldn't read file",
-                )
-            })?),
+            strategy(
+                tokio::fs::read_to_string(&requested_path)
+                    .await
+                    .map_err(|error| {
+                        error_to_response(
+                            error,
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "couldn't read file",
+                        )
+                    })?,
+            ),
         )
             .into_response())
     } else {
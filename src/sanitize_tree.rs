@@ -0,0 +1,1595 @@
+// Sanitizes a whole folder of raw logs at once, for mirroring them somewhere static
+// instead of sanitizing every request as it comes in.
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use eyre::Context;
+use flate2::{write::GzEncoder, Compression};
+use regex::Regex;
+
+use crate::{
+    ongoing_round_protection::{round_id_of_folder, OngoingRoundsSnapshot},
+    parsers::{read_to_string, SanitizationPolicy, Strategy},
+};
+
+// Lets a round folder skip files or pick different strategies for them, for when
+// something leaked that the normal rules miss. Applies to everything under the folder
+// it's in, and wins over the normal strategies. A deeper override file replaces it.
+pub const ROUND_OVERRIDE_FILENAME: &str = ".sanitize-override.toml";
+
+#[derive(Clone, Debug)]
+pub struct SanitizeTreeOptions {
+    // How many more times to try reading a file after the first failure
+    pub read_retries: u32,
+    pub retry_delay: Duration,
+
+    pub policy: SanitizationPolicy,
+
+    // Round folders that are still ongoing according to this are left out entirely
+    pub ongoing_rounds: Option<OngoingRoundsSnapshot>,
+
+    // Round folders with an id at or below this are left out entirely, for only catching up on new rounds.
+    // Ids are read with ongoing_rounds' round_folder_pattern if there is one, otherwise as round-<id>.
+    pub since_round: Option<u64>,
+
+    // When set, only files with names matching this are looked at, for re-sanitizing a few kinds of logs
+    pub only_filenames: Option<Regex>,
+
+    // The input is already sanitized, such as published logs being put through newer rules, see Strategy::resanitize
+    pub resanitize: bool,
+
+    // Keeps a FileRecord for every file in Summary::manifest. For big runs, stream them
+    // with sanitize_tree_with_manifest instead.
+    pub collect_manifest: bool,
+
+    // Write every file gzipped at this level (0 to 9) with .gz added to the name, instead of as plain text.
+    // Only used by sanitize_tree and sanitize_tree_with_manifest, other sinks decide for themselves.
+    pub gzip_level: Option<u32>,
+
+    // Files without a sanitization strategy are copied here as they are, for someone to look at
+    // and give them one, rather than being left out without anyone noticing. Never publish it.
+    pub quarantine_root: Option<PathBuf>,
+
+    // Files bigger than this many bytes are left out without being read
+    pub max_file_size: Option<u64>,
+
+    // Asked about every file in a round folder, for holding back spoilers like dynamic.json.
+    // Without one, everything is published.
+    pub round_file_gate: Option<RoundFileGate>,
+}
+
+impl Default for SanitizeTreeOptions {
+    fn default() -> Self {
+        Self {
+            read_retries: 2,
+            retry_delay: Duration::from_millis(500),
+            policy: SanitizationPolicy::default(),
+            ongoing_rounds: None,
+            since_round: None,
+            only_filenames: None,
+            resanitize: false,
+            collect_manifest: false,
+            gzip_level: None,
+            quarantine_root: None,
+            max_file_size: None,
+            round_file_gate: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundFileDecision {
+    #[default]
+    Publish,
+
+    // Not yet, such as until some time after the round. Left out of this run, for a later one to publish.
+    Defer,
+
+    // Never, such as for every round of a certain type
+    Withhold,
+}
+
+pub type RoundFileGateFn = dyn Fn(&Path, &str) -> RoundFileDecision + Send + Sync;
+
+// A caller's decision on each file of a round, given the round folder and the file's name.
+// This is for spoilers rather than privacy, so it comes after ongoing round protection, not instead of it.
+#[derive(Clone)]
+pub struct RoundFileGate(Arc<RoundFileGateFn>);
+
+impl RoundFileGate {
+    pub fn new(gate: impl Fn(&Path, &str) -> RoundFileDecision + Send + Sync + 'static) -> Self {
+        Self(Arc::new(gate))
+    }
+
+    // Publishes everything, the same as not having a gate
+    pub fn allow_all() -> Self {
+        Self::new(|_, _| RoundFileDecision::Publish)
+    }
+
+    pub fn decide(&self, round_directory: &Path, filename: &str) -> RoundFileDecision {
+        (self.0)(round_directory, filename)
+    }
+}
+
+impl fmt::Debug for RoundFileGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RoundFileGate")
+    }
+}
+
+// Known-good starting points, so that operators don't have to work out every option for themselves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    // What the official tgstation public logs use, which is the same as the server
+    TgstationPublic,
+
+    // Publishes as little as possible: strict mode, paper writing, comms messages, and base64 blobs censored,
+    // and nothing over 256 MiB, which no real log gets near
+    StrictMinimal,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::TgstationPublic => "tgstation_public",
+            Profile::StrictMinimal => "strict_minimal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Profile::TgstationPublic, Profile::StrictMinimal]
+            .into_iter()
+            .find(|profile| profile.name() == name)
+    }
+}
+
+impl SanitizeTreeOptions {
+    pub fn from_profile(profile: Profile) -> Self {
+        match profile {
+            Profile::TgstationPublic => Self::default(),
+
+            Profile::StrictMinimal => {
+                let mut options = Self {
+                    max_file_size: Some(256 * 1024 * 1024),
+                    ..Self::default()
+                };
+
+                options.policy.apply_strict_mode();
+                options.policy.paper.censor_content = true;
+                options.policy.comms.censor_messages = true;
+                options.policy.passthrough.base64_min_length = Some(64);
+
+                options
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub files_written: usize,
+
+    // Files without a sanitization strategy, which are never published
+    pub files_skipped: usize,
+
+    // Files without a sanitization strategy that were copied to quarantine_root
+    pub files_quarantined: usize,
+
+    // Files left out for being over max_file_size
+    pub files_oversized: usize,
+
+    // Files skipped because of a round override
+    pub files_overridden: usize,
+
+    // Round folders left out because the round is still going
+    pub rounds_ongoing: usize,
+
+    // Round folders left out for being at or below since_round
+    pub rounds_before_since: usize,
+
+    // Files left out because they didn't match only_filenames
+    pub files_filtered: usize,
+
+    // Files the round_file_gate deferred or withheld
+    pub files_deferred: usize,
+    pub files_withheld: usize,
+
+    // Files we couldn't read even after retrying, which were left out
+    pub failures: Vec<FileFailure>,
+
+    // For every strategy that sanitized anything, by name, for seeing which one a run spends its time in
+    pub strategy_stats: BTreeMap<&'static str, StrategyStats>,
+
+    // Only filled in with SanitizeTreeOptions::collect_manifest
+    pub manifest: Vec<FileRecord>,
+}
+
+impl Summary {
+    pub fn log_strategy_stats(&self) {
+        for (strategy, stats) in &self.strategy_stats {
+            tracing::info!(
+                "{strategy}: {} file(s), {} bytes in, {} bytes out, {:.2?} sanitizing, {}",
+                stats.files,
+                stats.bytes_in,
+                stats.bytes_out,
+                stats.elapsed,
+                match stats.megabytes_per_second() {
+                    Some(throughput) => format!("{throughput:.1} MB/s"),
+                    None => "too fast to measure".to_owned(),
+                }
+            );
+        }
+    }
+}
+
+// Only counts the time spent sanitizing, not reading or writing, so it's the strategy's own speed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StrategyStats {
+    pub files: usize,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub elapsed: Duration,
+}
+
+impl StrategyStats {
+    // Of input, None if it took no measurable time
+    pub fn megabytes_per_second(&self) -> Option<f64> {
+        let seconds = self.elapsed.as_secs_f64();
+        (seconds > 0.0).then(|| self.bytes_in as f64 / 1_000_000.0 / seconds)
+    }
+}
+
+#[derive(Debug)]
+pub struct FileFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+// What happened to a single file or round folder, for the manifest
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct FileRecord {
+    // Relative to the input root
+    pub path: PathBuf,
+    pub status: FileStatus,
+
+    // The file or folder name as it was, and what it was written as, which can be different,
+    // like with gzip_level. For redirecting from one to the other.
+    pub input_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<&'static str>,
+
+    // Size of the sanitized output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Written,
+    Skipped,
+    Overridden,
+    Filtered,
+    Quarantined,
+    Oversized,
+    Deferred,
+    Withheld,
+    RoundOngoing,
+    RoundBeforeSince,
+    Failed,
+}
+
+impl FileRecord {
+    fn new(path: PathBuf, status: FileStatus) -> Self {
+        Self {
+            input_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            output_name: None,
+            path,
+            status,
+            strategy: None,
+            bytes: None,
+            error: None,
+        }
+    }
+}
+
+// Paths are relative to the folder the override file is in
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RoundOverride {
+    #[serde(default)]
+    skip: Vec<PathBuf>,
+
+    // Path to strategy name, as in Strategy::name
+    #[serde(default)]
+    strategies: HashMap<PathBuf, String>,
+}
+
+impl RoundOverride {
+    fn load(path: &Path) -> eyre::Result<Self> {
+        let round_override: RoundOverride = toml::from_str(&std::fs::read_to_string(path)?)?;
+
+        for strategy_name in round_override.strategies.values() {
+            if Strategy::from_name(strategy_name).is_none() {
+                eyre::bail!("unknown strategy {strategy_name}");
+            }
+        }
+
+        Ok(round_override)
+    }
+}
+
+// Where sanitized files go, for writing them somewhere other than a folder, like a zip or an HTTP body
+pub trait SanitizationSink {
+    // relative_path is where the file was relative to the input root, after its strategy's Strategy::output_path
+    fn write_sanitized(&mut self, relative_path: &Path, contents: &[u8]) -> eyre::Result<()>;
+
+    // Where write_sanitized puts relative_path, relative to wherever the sink writes, for the manifest
+    fn output_path(&self, relative_path: &Path) -> PathBuf {
+        relative_path.to_owned()
+    }
+}
+
+// Mirrors the input tree under output_root
+pub struct FilesystemSink {
+    pub output_root: PathBuf,
+
+    // See SanitizeTreeOptions::gzip_level
+    pub gzip_level: Option<u32>,
+}
+
+impl FilesystemSink {
+    pub fn new(output_root: impl Into<PathBuf>) -> Self {
+        Self {
+            output_root: output_root.into(),
+            gzip_level: None,
+        }
+    }
+}
+
+impl SanitizationSink for FilesystemSink {
+    fn write_sanitized(&mut self, relative_path: &Path, contents: &[u8]) -> eyre::Result<()> {
+        let output_path = self.output_root.join(self.output_path(relative_path));
+
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+
+        write_output(&output_path, contents, self.gzip_level)
+            .with_context(|| format!("writing {}", output_path.display()))
+    }
+
+    fn output_path(&self, relative_path: &Path) -> PathBuf {
+        if self.gzip_level.is_none() {
+            return relative_path.to_owned();
+        }
+
+        let mut gzip_path = relative_path.as_os_str().to_owned();
+        gzip_path.push(".gz");
+        PathBuf::from(gzip_path)
+    }
+}
+
+pub fn sanitize_tree(
+    input_root: &Path,
+    output_root: &Path,
+    options: &SanitizeTreeOptions,
+) -> eyre::Result<Summary> {
+    check_quarantine_root(output_root, options)?;
+
+    let mut sink = FilesystemSink {
+        output_root: output_root.to_owned(),
+        gzip_level: options.gzip_level,
+    };
+
+    sanitize_tree_to_sink(input_root, options, &mut sink)
+}
+
+// quarantine_root isn't checked against where the sink writes, so make sure it's somewhere unpublished
+pub fn sanitize_tree_to_sink(
+    input_root: &Path,
+    options: &SanitizeTreeOptions,
+    sink: &mut dyn SanitizationSink,
+) -> eyre::Result<Summary> {
+    let mut tree_walk = TreeWalk {
+        input_root,
+        sink,
+        options,
+        summary: Summary::default(),
+        manifest_writer: None,
+    };
+
+    tree_walk.sanitize_directory(input_root, None, None)?;
+
+    Ok(tree_walk.summary)
+}
+
+// Writes a FileRecord as a line of JSON as soon as each file is done, so memory stays flat
+// and the run can be followed with something like tail -f.
+pub fn sanitize_tree_with_manifest(
+    input_root: &Path,
+    output_root: &Path,
+    options: &SanitizeTreeOptions,
+    manifest_writer: &mut dyn Write,
+) -> eyre::Result<Summary> {
+    check_quarantine_root(output_root, options)?;
+
+    let mut sink = FilesystemSink {
+        output_root: output_root.to_owned(),
+        gzip_level: options.gzip_level,
+    };
+
+    let mut tree_walk = TreeWalk {
+        input_root,
+        sink: &mut sink,
+        options,
+        summary: Summary::default(),
+        manifest_writer: Some(manifest_writer),
+    };
+
+    tree_walk.sanitize_directory(input_root, None, None)?;
+
+    if let Some(manifest_writer) = tree_walk.manifest_writer {
+        manifest_writer.flush().context("flushing manifest")?;
+    }
+
+    Ok(tree_walk.summary)
+}
+
+struct TreeWalk<'a> {
+    input_root: &'a Path,
+    sink: &'a mut dyn SanitizationSink,
+    options: &'a SanitizeTreeOptions,
+    summary: Summary,
+    manifest_writer: Option<&'a mut dyn Write>,
+}
+
+impl TreeWalk<'_> {
+    fn record(&mut self, mut record: FileRecord) -> eyre::Result<()> {
+        if let Ok(relative_path) = record.path.strip_prefix(self.input_root) {
+            record.path = relative_path.to_owned();
+        }
+
+        if let Some(manifest_writer) = &mut self.manifest_writer {
+            serde_json::to_writer(&mut *manifest_writer, &record)?;
+            manifest_writer
+                .write_all(b"\n")
+                .context("writing manifest")?;
+        }
+
+        if self.options.collect_manifest {
+            self.summary.manifest.push(record);
+        }
+
+        Ok(())
+    }
+
+    fn sanitize_directory(
+        &mut self,
+        directory: &Path,
+        round_override: Option<(&Path, &RoundOverride)>,
+        round_directory: Option<&Path>,
+    ) -> eyre::Result<()> {
+        if let Some(ongoing_rounds) = &self.options.ongoing_rounds {
+            match ongoing_rounds.path_is_ongoing_round(directory) {
+                Ok(false) => {}
+
+                Ok(true) => {
+                    tracing::debug!("{} is ongoing, skipping it", directory.display());
+                    self.summary.rounds_ongoing += 1;
+                    return self.record(FileRecord::new(
+                        directory.to_owned(),
+                        FileStatus::RoundOngoing,
+                    ));
+                }
+
+                // Same as the server, which won't serve anything it can't tell is finished
+                Err(error) => {
+                    tracing::error!(
+                        "couldn't tell if {} is ongoing, skipping it: {error:?}",
+                        directory.display()
+                    );
+                    return self.fail(directory.to_owned(), format!("{error:#}"));
+                }
+            }
+        }
+
+        if let Some(since_round) = self.options.since_round {
+            let folder_name = directory
+                .file_name()
+                .map(|folder_name| folder_name.to_string_lossy())
+                .unwrap_or_default();
+
+            let round_id = match &self.options.ongoing_rounds {
+                Some(ongoing_rounds) => ongoing_rounds.round_id_of_folder(&folder_name),
+                None => round_id_of_folder(&folder_name),
+            };
+
+            match round_id {
+                Ok(Some(round_id)) if round_id <= since_round => {
+                    tracing::debug!(
+                        "{} is before round {since_round}, skipping it",
+                        directory.display()
+                    );
+                    self.summary.rounds_before_since += 1;
+                    return self.record(FileRecord::new(
+                        directory.to_owned(),
+                        FileStatus::RoundBeforeSince,
+                    ));
+                }
+
+                Ok(_) => {}
+
+                Err(error) => {
+                    tracing::error!(
+                        "couldn't tell which round {} is, skipping it: {error:?}",
+                        directory.display()
+                    );
+                    return self.fail(directory.to_owned(), format!("{error:#}"));
+                }
+            }
+        }
+
+        let override_path = directory.join(ROUND_OVERRIDE_FILENAME);
+        let loaded_override;
+        let round_override = if override_path.exists() {
+            match RoundOverride::load(&override_path) {
+                Ok(round_override) => {
+                    loaded_override = round_override;
+                    Some((directory, &loaded_override))
+                }
+
+                // Better to leave out the whole round than to publish something the override was for
+                Err(error) => {
+                    tracing::error!(
+                        "invalid round override {}, skipping the round: {error:?}",
+                        override_path.display()
+                    );
+                    return self.fail(override_path, format!("{error:#}"));
+                }
+            }
+        } else {
+            round_override
+        };
+
+        let round_directory = if self.is_round_folder(directory) {
+            Some(directory)
+        } else {
+            round_directory
+        };
+
+        // Without the root there's nothing to sanitize, but one unreadable folder shouldn't stop the rest
+        let read_dir = match std::fs::read_dir(directory) {
+            Ok(read_dir) => read_dir,
+            Err(error) if directory == self.input_root => {
+                return Err(error)
+                    .with_context(|| format!("reading directory {}", directory.display()));
+            }
+            Err(error) => {
+                tracing::error!("error reading directory {}: {error}", directory.display());
+                return self.fail(directory.to_owned(), format!("reading directory: {error}"));
+            }
+        };
+
+        for entry in read_dir {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    tracing::error!("error reading directory {}: {error}", directory.display());
+                    self.fail(directory.to_owned(), format!("reading directory: {error}"))?;
+                    continue;
+                }
+            };
+            let path = entry.path();
+
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(error) => {
+                    tracing::error!("error reading file type of {}: {error}", path.display());
+                    self.fail(path, format!("reading file type: {error}"))?;
+                    continue;
+                }
+            };
+
+            if file_type.is_dir() {
+                self.sanitize_directory(&path, round_override, round_directory)?;
+                continue;
+            }
+
+            if let Some(only_filenames) = &self.options.only_filenames {
+                if !only_filenames.is_match(&entry.file_name().to_string_lossy()) {
+                    self.summary.files_filtered += 1;
+                    self.record(FileRecord::new(path, FileStatus::Filtered))?;
+                    continue;
+                }
+            }
+
+            if let (Some(round_file_gate), Some(round_directory)) =
+                (&self.options.round_file_gate, round_directory)
+            {
+                match round_file_gate.decide(round_directory, &entry.file_name().to_string_lossy())
+                {
+                    RoundFileDecision::Publish => {}
+
+                    RoundFileDecision::Defer => {
+                        self.summary.files_deferred += 1;
+                        self.record(FileRecord::new(path, FileStatus::Deferred))?;
+                        continue;
+                    }
+
+                    RoundFileDecision::Withhold => {
+                        self.summary.files_withheld += 1;
+                        self.record(FileRecord::new(path, FileStatus::Withheld))?;
+                        continue;
+                    }
+                }
+            }
+
+            let strategy = match round_override {
+                Some((override_directory, round_override)) => {
+                    let relative_path = path.strip_prefix(override_directory)?;
+
+                    if round_override.skip.iter().any(|skip| skip == relative_path) {
+                        self.summary.files_overridden += 1;
+                        self.record(FileRecord::new(path, FileStatus::Overridden))?;
+                        continue;
+                    }
+
+                    match round_override.strategies.get(relative_path) {
+                        Some(strategy_name) => Strategy::from_name(strategy_name),
+                        None => self.options.policy.sanitization_strategy(&path),
+                    }
+                }
+
+                None => self.options.policy.sanitization_strategy(&path),
+            };
+
+            let Some(strategy) = strategy else {
+                match &self.options.quarantine_root {
+                    Some(quarantine_root) => {
+                        let quarantine_path =
+                            quarantine_root.join(path.strip_prefix(self.input_root)?);
+                        self.quarantine_file(path, &quarantine_path)?;
+                    }
+
+                    None => {
+                        self.summary.files_skipped += 1;
+                        self.record(FileRecord::new(path, FileStatus::Skipped))?;
+                    }
+                }
+
+                continue;
+            };
+
+            self.sanitize_file(path, strategy)?;
+        }
+
+        Ok(())
+    }
+
+    // Folders that don't parse as a round aren't one here, since_round is what fails them
+    fn is_round_folder(&self, directory: &Path) -> bool {
+        let Some(folder_name) = directory.file_name() else {
+            return false;
+        };
+        let folder_name = folder_name.to_string_lossy();
+
+        let round_id = match &self.options.ongoing_rounds {
+            Some(ongoing_rounds) => ongoing_rounds.round_id_of_folder(&folder_name),
+            None => round_id_of_folder(&folder_name),
+        };
+
+        matches!(round_id, Ok(Some(_)))
+    }
+
+    // Covers reading, sanitizing, and writing, so that profiling shows which files take the longest
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            filename = %path.display(),
+            strategy = strategy.name(),
+            bytes = tracing::field::Empty,
+        )
+    )]
+    fn sanitize_file(&mut self, path: PathBuf, strategy: Strategy) -> eyre::Result<()> {
+        let contents = match read_with_retries(&path, self.options) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::FileTooLarge => {
+                tracing::warn!("{} is too big, skipping it: {error}", path.display());
+                self.summary.files_oversized += 1;
+                return self.record(FileRecord {
+                    error: Some(error.to_string()),
+                    ..FileRecord::new(path, FileStatus::Oversized)
+                });
+            }
+            Err(error) => {
+                tracing::error!("couldn't read {}, skipping it: {error}", path.display());
+                return self.fail(path, error.to_string());
+            }
+        };
+
+        tracing::Span::current().record("bytes", contents.len());
+
+        let bytes_in = contents.len();
+        let started = Instant::now();
+        let sanitized = if self.options.resanitize {
+            strategy.resanitize(&path, contents, &self.options.policy)
+        } else {
+            strategy.sanitize(&path, contents, &self.options.policy)
+        };
+        let bytes = sanitized.len();
+        let relative_path = path.strip_prefix(self.input_root)?;
+        let renamed_path = strategy.output_path(relative_path);
+        let output_name = self
+            .sink
+            .output_path(&renamed_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+
+        let stats = self
+            .summary
+            .strategy_stats
+            .entry(strategy.name())
+            .or_default();
+        stats.files += 1;
+        stats.bytes_in += bytes_in as u64;
+        stats.bytes_out += bytes as u64;
+        stats.elapsed += started.elapsed();
+        self.sink
+            .write_sanitized(&renamed_path, sanitized.as_bytes())?;
+
+        self.summary.files_written += 1;
+        self.record(FileRecord {
+            strategy: Some(strategy.name()),
+            bytes: Some(bytes),
+            output_name,
+            ..FileRecord::new(path, FileStatus::Written)
+        })
+    }
+
+    fn quarantine_file(&mut self, path: PathBuf, quarantine_path: &Path) -> eyre::Result<()> {
+        let copied = quarantine_path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::copy(&path, quarantine_path));
+
+        if let Err(error) = copied {
+            tracing::error!("couldn't quarantine {}: {error}", path.display());
+            return self.fail(path, error.to_string());
+        }
+
+        self.summary.files_quarantined += 1;
+        self.record(FileRecord::new(path, FileStatus::Quarantined))
+    }
+
+    fn fail(&mut self, path: PathBuf, error: String) -> eyre::Result<()> {
+        self.record(FileRecord {
+            error: Some(error.clone()),
+            ..FileRecord::new(path.clone(), FileStatus::Failed)
+        })?;
+        self.summary.failures.push(FileFailure { path, error });
+        Ok(())
+    }
+}
+
+// Quarantined files aren't sanitized, so they can't end up anywhere that's published
+// Both are created and canonicalized first, so "out/../out/q", symlinks, and relative paths can't get past it
+fn check_quarantine_root(output_root: &Path, options: &SanitizeTreeOptions) -> eyre::Result<()> {
+    fn canonicalize(path: &Path) -> eyre::Result<PathBuf> {
+        std::fs::create_dir_all(path)
+            .and_then(|_| path.canonicalize())
+            .with_context(|| format!("resolving {}", path.display()))
+    }
+
+    if let Some(quarantine_root) = &options.quarantine_root {
+        if canonicalize(quarantine_root)?.starts_with(canonicalize(output_root)?) {
+            eyre::bail!(
+                "quarantine root {} is inside the output root",
+                quarantine_root.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn write_output(path: &Path, contents: &[u8], gzip_level: Option<u32>) -> std::io::Result<()> {
+    let Some(gzip_level) = gzip_level else {
+        return std::fs::write(path, contents);
+    };
+
+    let mut encoder = GzEncoder::new(std::fs::File::create(path)?, Compression::new(gzip_level));
+    encoder.write_all(contents)?;
+    encoder.finish()?;
+
+    Ok(())
+}
+
+// Errors that might go away by themselves, like on a network mount. Anything else, like a file that
+// isn't UTF-8 or isn't there, will fail the same way every time.
+fn is_transient(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    matches!(
+        error.kind(),
+        ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::TimedOut
+            | ErrorKind::ResourceBusy
+            | ErrorKind::StaleNetworkFileHandle
+    )
+}
+
+fn read_with_retries(path: &Path, options: &SanitizeTreeOptions) -> std::io::Result<String> {
+    let mut attempt = 0;
+
+    loop {
+        match read_to_string(path, options.max_file_size) {
+            Ok(contents) => return Ok(contents),
+            Err(error) if attempt < options.read_retries && is_transient(&error) => {
+                attempt += 1;
+                tracing::warn!(
+                    "error reading {} (attempt {attempt}), retrying: {error}",
+                    path.display()
+                );
+                std::thread::sleep(options.retry_delay);
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "tg-public-log-parser-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    fn test_options() -> SanitizeTreeOptions {
+        SanitizeTreeOptions {
+            read_retries: 2,
+            retry_delay: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_continues_past_unreadable_files() {
+        let input = test_directory("unreadable-input");
+        let output = test_directory("unreadable-output");
+
+        let round = input.join("2024/01/02/round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(
+            round.join("game.log"),
+            "[2024-01-02 03:04:05.678] GAME: from 1.2.3.4\n",
+        )
+        .unwrap();
+        // Not UTF-8, so it can never be read as a string
+        std::fs::write(round.join("attack.log"), [0xff, 0xfe, 0xfd]).unwrap();
+        std::fs::write(round.join("secret.log"), "not published").unwrap();
+
+        let summary = sanitize_tree(&input, &output, &test_options()).unwrap();
+
+        assert_eq!(summary.files_written, 1);
+        assert_eq!(summary.files_skipped, 1);
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].path, round.join("attack.log"));
+
+        assert_eq!(
+            std::fs::read_to_string(output.join("2024/01/02/round-1/game.log")).unwrap(),
+            "[2024-01-02 03:04:05.678] GAME: from -censored-\n"
+        );
+        assert!(!output.join("2024/01/02/round-1/attack.log").exists());
+        assert!(!output.join("2024/01/02/round-1/secret.log").exists());
+
+        std::fs::remove_dir_all(&input).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_permanent_errors_are_not_retried() {
+        let input = test_directory("permanent-error-input");
+        let output = test_directory("permanent-error-output");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(round.join("attack.log"), [0xff, 0xfe, 0xfd]).unwrap();
+
+        let options = SanitizeTreeOptions {
+            read_retries: 2,
+            retry_delay: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        let started = std::time::Instant::now();
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(summary.failures.len(), 1);
+
+        assert!(is_transient(&std::io::ErrorKind::Interrupted.into()));
+        assert!(!is_transient(&std::io::ErrorKind::NotFound.into()));
+        assert!(!is_transient(&std::io::ErrorKind::InvalidData.into()));
+
+        std::fs::remove_dir_all(&input).unwrap();
+        let _ = std::fs::remove_dir_all(&output);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_continues_past_unreadable_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let input = test_directory("unreadable-directory-input");
+        let output = test_directory("unreadable-directory-output");
+
+        let unreadable = input.join("round-1");
+        let readable = input.join("round-2");
+        for round in [&unreadable, &readable] {
+            std::fs::create_dir_all(round).unwrap();
+            std::fs::write(round.join("attack.log"), "from 1.2.3.4\n").unwrap();
+        }
+        std::fs::set_permissions(&unreadable, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Permissions don't stop root, so there's nothing to check
+        if std::fs::read_dir(&unreadable).is_err() {
+            let summary = sanitize_tree(&input, &output, &test_options()).unwrap();
+
+            assert_eq!(summary.files_written, 1);
+            assert_eq!(summary.failures.len(), 1);
+            assert_eq!(summary.failures[0].path, unreadable);
+            assert!(output.join("round-2/attack.log").exists());
+        }
+
+        std::fs::set_permissions(&unreadable, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(sanitize_tree(&input.join("missing"), &output, &test_options()).is_err());
+
+        std::fs::remove_dir_all(&input).unwrap();
+        let _ = std::fs::remove_dir_all(&output);
+    }
+
+    #[test]
+    fn test_round_override() {
+        let input = test_directory("override-input");
+        let output = test_directory("override-output");
+
+        let overridden_round = input.join("round-1");
+        let normal_round = input.join("round-2");
+
+        for round in [&overridden_round, &normal_round] {
+            std::fs::create_dir_all(round).unwrap();
+            std::fs::write(round.join("paper.log"), "something leaked").unwrap();
+            std::fs::write(round.join("attack.log"), "[12:00:00] TOPIC: secret\n").unwrap();
+        }
+
+        std::fs::write(
+            overridden_round.join(ROUND_OVERRIDE_FILENAME),
+            r#"
+                skip = ["paper.log"]
+
+                [strategies]
+                "attack.log" = "game"
+            "#,
+        )
+        .unwrap();
+
+        let summary = sanitize_tree(&input, &output, &test_options()).unwrap();
+
+        assert_eq!(summary.files_written, 3);
+        assert_eq!(summary.files_overridden, 1);
+        assert!(summary.failures.is_empty());
+
+        assert!(!output.join("round-1/paper.log").exists());
+        assert_eq!(
+            std::fs::read_to_string(output.join("round-1/attack.log")).unwrap(),
+            "-censored(world_topic logs)-\n"
+        );
+
+        assert!(output.join("round-2/paper.log").exists());
+        assert_eq!(
+            std::fs::read_to_string(output.join("round-2/attack.log")).unwrap(),
+            "[12:00:00] TOPIC: secret\n"
+        );
+
+        std::fs::remove_dir_all(&input).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_round_override_skips_round() {
+        let input = test_directory("invalid-override-input");
+        let output = test_directory("invalid-override-output");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(round.join("paper.log"), "something leaked").unwrap();
+        std::fs::write(
+            round.join(ROUND_OVERRIDE_FILENAME),
+            r#"strategies = { "paper.log" = "not a strategy" }"#,
+        )
+        .unwrap();
+
+        let summary = sanitize_tree(&input, &output, &test_options()).unwrap();
+
+        assert_eq!(summary.files_written, 0);
+        assert_eq!(summary.failures.len(), 1);
+        assert!(!output.join("round-1/paper.log").exists());
+
+        std::fs::remove_dir_all(&input).unwrap();
+        let _ = std::fs::remove_dir_all(&output);
+    }
+    #[test]
+    fn test_only_filenames() {
+        let input = test_directory("only-filenames-input");
+        let output = test_directory("only-filenames-output");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        for filename in ["game.log", "runtime.log", "paper.log", "attack.log"] {
+            std::fs::write(round.join(filename), "[12:00:00] GAME: hello\n").unwrap();
+        }
+
+        let options = SanitizeTreeOptions {
+            only_filenames: Some(Regex::new(r"^(game|runtime)\.log$").unwrap()),
+            ..test_options()
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+
+        assert_eq!(summary.files_written, 2);
+        assert_eq!(summary.files_filtered, 2);
+        assert!(output.join("round-1/game.log").exists());
+        assert!(output.join("round-1/runtime.log").exists());
+        assert!(!output.join("round-1/paper.log").exists());
+
+        std::fs::remove_dir_all(&input).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_skips_ongoing_rounds() {
+        let input = test_directory("ongoing-input");
+        let output = test_directory("ongoing-output");
+
+        for round in ["round-1", "round-2"] {
+            std::fs::create_dir_all(input.join(round)).unwrap();
+            std::fs::write(input.join(round).join("paper.log"), "a paper").unwrap();
+        }
+
+        let config =
+            toml::from_str(r#"serverinfo = "https://example.com/serverinfo.json""#).unwrap();
+        let options = SanitizeTreeOptions {
+            ongoing_rounds: Some(OngoingRoundsSnapshot::new(
+                config,
+                HashMap::from([("server".to_owned(), 2)]),
+            )),
+            ..test_options()
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+
+        assert_eq!(summary.files_written, 1);
+        assert_eq!(summary.rounds_ongoing, 1);
+        assert!(output.join("round-1/paper.log").exists());
+        assert!(!output.join("round-2").exists());
+
+        std::fs::remove_dir_all(&input).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_since_round() {
+        let input = test_directory("since-round-input");
+        let output = test_directory("since-round-output");
+
+        for round in ["round-9", "round-10", "round-11", "round-12"] {
+            std::fs::create_dir_all(input.join(round)).unwrap();
+            std::fs::write(input.join(round).join("paper.log"), "a paper").unwrap();
+        }
+
+        let options = SanitizeTreeOptions {
+            since_round: Some(10),
+            ..test_options()
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+        assert_eq!(summary.files_written, 2);
+        assert_eq!(summary.rounds_before_since, 2);
+        assert!(!output.join("round-9").exists());
+        assert!(!output.join("round-10").exists());
+        assert!(output.join("round-11/paper.log").exists());
+        std::fs::remove_dir_all(&output).unwrap();
+
+        // Along with ongoing rounds, with ids read using the config's pattern
+        let config = toml::from_str(
+            r#"
+            serverinfo = "https://example.com/serverinfo.json"
+            round_folder_pattern = '^round-([0-9]+)$'
+            "#,
+        )
+        .unwrap();
+        let options = SanitizeTreeOptions {
+            ongoing_rounds: Some(OngoingRoundsSnapshot::new(
+                config,
+                HashMap::from([("server".to_owned(), 12)]),
+            )),
+            ..options
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+        assert_eq!(summary.files_written, 1);
+        assert_eq!(summary.rounds_before_since, 2);
+        assert_eq!(summary.rounds_ongoing, 1);
+        assert!(output.join("round-11/paper.log").exists());
+        assert!(!output.join("round-12").exists());
+
+        std::fs::remove_dir_all(&input).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_manifest() {
+        let input = test_directory("manifest-input");
+        let output = test_directory("manifest-output");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(round.join("game.log"), "[12:00:00] GAME: hello\n").unwrap();
+        std::fs::write(round.join("secret.log"), "not published").unwrap();
+        std::fs::write(round.join("attack.txt"), "hello\n").unwrap();
+
+        let options = SanitizeTreeOptions {
+            collect_manifest: true,
+            ..test_options()
+        };
+
+        let mut streamed = Vec::new();
+        let summary =
+            sanitize_tree_with_manifest(&input, &output, &options, &mut streamed).unwrap();
+
+        let mut manifest = summary.manifest;
+        manifest.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            manifest,
+            [
+                FileRecord {
+                    strategy: Some("passthrough_sanitized"),
+                    bytes: Some(6),
+                    output_name: Some("attack.log".to_owned()),
+                    ..FileRecord::new(PathBuf::from("round-1/attack.txt"), FileStatus::Written)
+                },
+                FileRecord {
+                    strategy: Some("game"),
+                    bytes: Some(23),
+                    output_name: Some("game.log".to_owned()),
+                    ..FileRecord::new(PathBuf::from("round-1/game.log"), FileStatus::Written)
+                },
+                FileRecord::new(PathBuf::from("round-1/secret.log"), FileStatus::Skipped),
+            ]
+        );
+
+        let streamed = String::from_utf8(streamed).unwrap();
+        let mut lines: Vec<&str> = streamed.lines().collect();
+        lines.sort();
+        assert_eq!(
+            lines,
+            [
+                r#"{"path":"round-1/attack.txt","status":"written","input_name":"attack.txt","output_name":"attack.log","strategy":"passthrough_sanitized","bytes":6}"#,
+                r#"{"path":"round-1/game.log","status":"written","input_name":"game.log","output_name":"game.log","strategy":"game","bytes":23}"#,
+                r#"{"path":"round-1/secret.log","status":"skipped","input_name":"secret.log"}"#,
+            ]
+        );
+
+        std::fs::remove_dir_all(&input).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_gzip_output() {
+        let input = test_directory("gzip-input");
+        let output = test_directory("gzip-output");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(round.join("game.log"), "[12:00:00] GAME: from 1.2.3.4\n").unwrap();
+        std::fs::write(round.join("dynamic.json"), "{}").unwrap();
+        std::fs::write(round.join("attack.txt"), "from 1.2.3.4\n").unwrap();
+
+        let options = SanitizeTreeOptions {
+            gzip_level: Some(6),
+            collect_manifest: true,
+            ..test_options()
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+        assert_eq!(summary.files_written, 3);
+        assert!(summary.manifest.iter().any(|record| {
+            record.input_name == "game.log" && record.output_name.as_deref() == Some("game.log.gz")
+        }));
+        assert!(summary.manifest.iter().any(|record| {
+            record.input_name == "attack.txt"
+                && record.output_name.as_deref() == Some("attack.log.gz")
+        }));
+        assert!(!output.join("round-1/game.log").exists());
+        assert!(output.join("round-1/dynamic.json.gz").exists());
+        assert!(output.join("round-1/attack.log.gz").exists());
+        assert!(!output.join("round-1/attack.txt.gz").exists());
+
+        let mut decoded = String::new();
+        std::io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(
+                std::fs::File::open(output.join("round-1/game.log.gz")).unwrap(),
+            ),
+            &mut decoded,
+        )
+        .unwrap();
+        assert_eq!(decoded, "[12:00:00] GAME: from -censored-\n");
+
+        std::fs::remove_dir_all(&input).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_quarantine() {
+        let input = test_directory("quarantine-input");
+        let output = test_directory("quarantine-output");
+        let quarantine = test_directory("quarantine-quarantine");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(round.join("game.log"), "[12:00:00] GAME: hello\n").unwrap();
+        std::fs::write(round.join("new_thing.log"), "from 1.2.3.4").unwrap();
+
+        let options = SanitizeTreeOptions {
+            quarantine_root: Some(quarantine.clone()),
+            collect_manifest: true,
+            ..test_options()
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+
+        assert_eq!(summary.files_written, 1);
+        assert_eq!(summary.files_skipped, 0);
+        assert_eq!(summary.files_quarantined, 1);
+        assert!(summary.manifest.contains(&FileRecord::new(
+            PathBuf::from("round-1/new_thing.log"),
+            FileStatus::Quarantined
+        )));
+
+        assert!(!output.join("round-1/new_thing.log").exists());
+        assert_eq!(
+            std::fs::read_to_string(quarantine.join("round-1/new_thing.log")).unwrap(),
+            "from 1.2.3.4"
+        );
+        assert!(!quarantine.join("round-1/game.log").exists());
+
+        let inside_output = SanitizeTreeOptions {
+            quarantine_root: Some(output.join("quarantine")),
+            ..test_options()
+        };
+        assert!(sanitize_tree(&input, &output, &inside_output).is_err());
+
+        // The same place, spelled differently
+        let output_name = output.file_name().unwrap();
+        let roundabout = SanitizeTreeOptions {
+            quarantine_root: Some(output.join("..").join(output_name).join("quarantine")),
+            ..test_options()
+        };
+        assert!(sanitize_tree(&input, &output, &roundabout).is_err());
+
+        let roundabout_output = quarantine.join("..").join(output_name);
+        let inside_roundabout_output = SanitizeTreeOptions {
+            quarantine_root: Some(output.join("quarantine")),
+            ..test_options()
+        };
+        assert!(sanitize_tree(&input, &roundabout_output, &inside_roundabout_output).is_err());
+
+        let relative_output = PathBuf::from(format!(
+            "target/tg-public-log-parser-quarantine-relative-{}",
+            std::process::id()
+        ));
+        let absolute_inside_relative = SanitizeTreeOptions {
+            quarantine_root: Some(
+                std::env::current_dir()
+                    .unwrap()
+                    .join(&relative_output)
+                    .join("quarantine"),
+            ),
+            ..test_options()
+        };
+        assert!(sanitize_tree(&input, &relative_output, &absolute_inside_relative).is_err());
+        std::fs::remove_dir_all(relative_output).unwrap();
+
+        #[cfg(unix)]
+        {
+            let link = test_directory("quarantine-output-link");
+            std::os::unix::fs::symlink(&output, &link).unwrap();
+            let through_link = SanitizeTreeOptions {
+                quarantine_root: Some(link.join("quarantine")),
+                ..test_options()
+            };
+            assert!(sanitize_tree(&input, &output, &through_link).is_err());
+            std::fs::remove_file(link).unwrap();
+        }
+
+        for directory in [input, output, quarantine] {
+            std::fs::remove_dir_all(directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        let input = test_directory("max-file-size-input");
+        let output = test_directory("max-file-size-output");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::copy("fixtures/game-categories/game.log", round.join("game.log")).unwrap();
+        std::fs::write(round.join("attack.log"), "small\n").unwrap();
+
+        let options = SanitizeTreeOptions {
+            max_file_size: Some(100),
+            collect_manifest: true,
+            ..test_options()
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+
+        assert_eq!(summary.files_written, 1);
+        assert_eq!(summary.files_oversized, 1);
+        assert!(summary.failures.is_empty());
+        assert!(summary.manifest.iter().any(|record| {
+            record.path == Path::new("round-1/game.log") && record.status == FileStatus::Oversized
+        }));
+
+        assert!(!output.join("round-1/game.log").exists());
+        assert!(output.join("round-1/attack.log").exists());
+
+        let error = read_to_string(&round.join("game.log"), Some(100)).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::FileTooLarge);
+
+        for directory in [input, output] {
+            std::fs::remove_dir_all(directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_round_file_gate() {
+        let input = test_directory("round-file-gate-input");
+        let output = test_directory("round-file-gate-output");
+
+        for round_id in [1, 2] {
+            let round = input.join(format!("2024/01/02/round-{round_id}"));
+            std::fs::create_dir_all(round.join("profiler")).unwrap();
+            std::fs::write(round.join("game.log"), "[12:00:00] GAME: hello\n").unwrap();
+            std::fs::write(round.join("dynamic.json"), "{}").unwrap();
+            std::fs::write(round.join("round_end_data.json"), "{}").unwrap();
+            std::fs::write(round.join("profiler/profiler.json"), "{}").unwrap();
+        }
+        std::fs::write(input.join("2024/dynamic.json"), "{}").unwrap();
+
+        let options = SanitizeTreeOptions {
+            round_file_gate: Some(RoundFileGate::new(
+                |round_directory, filename| match filename {
+                    "dynamic.json" => RoundFileDecision::Defer,
+                    "round_end_data.json" | "profiler.json"
+                        if round_directory.ends_with("round-2") =>
+                    {
+                        RoundFileDecision::Withhold
+                    }
+                    _ => RoundFileDecision::Publish,
+                },
+            )),
+            ..test_options()
+        };
+
+        let summary = sanitize_tree(&input, &output, &options).unwrap();
+        assert_eq!(summary.files_deferred, 2);
+        assert_eq!(summary.files_withheld, 2);
+        assert_eq!(summary.files_written, 5);
+
+        assert!(output.join("2024/01/02/round-1/game.log").exists());
+        assert!(!output.join("2024/01/02/round-1/dynamic.json").exists());
+        assert!(output
+            .join("2024/01/02/round-1/round_end_data.json")
+            .exists());
+        assert!(output
+            .join("2024/01/02/round-1/profiler/profiler.json")
+            .exists());
+        assert!(!output
+            .join("2024/01/02/round-2/round_end_data.json")
+            .exists());
+        assert!(!output
+            .join("2024/01/02/round-2/profiler/profiler.json")
+            .exists());
+
+        // Only files in a round are asked about
+        assert!(output.join("2024/dynamic.json").exists());
+
+        let summary = sanitize_tree(
+            &input,
+            &test_directory("round-file-gate-all"),
+            &SanitizeTreeOptions {
+                round_file_gate: Some(RoundFileGate::allow_all()),
+                ..test_options()
+            },
+        )
+        .unwrap();
+        assert_eq!(summary.files_written, 9);
+
+        for directory in [input, output, test_directory("round-file-gate-all")] {
+            let _ = std::fs::remove_dir_all(directory);
+        }
+    }
+
+    #[test]
+    fn test_strategy_stats() {
+        let input = test_directory("strategy-stats-input");
+        let output = test_directory("strategy-stats-output");
+
+        for round_id in [1, 2] {
+            let round = input.join(format!("round-{round_id}"));
+            std::fs::create_dir_all(&round).unwrap();
+            std::fs::copy("fixtures/game-categories/game.log", round.join("game.log")).unwrap();
+            std::fs::write(round.join("attack.log"), "from 1.2.3.4\n").unwrap();
+        }
+
+        let summary = sanitize_tree(&input, &output, &test_options()).unwrap();
+        let game_log_size = std::fs::metadata("fixtures/game-categories/game.log")
+            .unwrap()
+            .len();
+
+        assert_eq!(
+            summary.strategy_stats.keys().copied().collect::<Vec<_>>(),
+            vec!["game", "passthrough_sanitized"]
+        );
+
+        let game = summary.strategy_stats["game"];
+        assert_eq!(game.files, 2);
+        assert_eq!(game.bytes_in, game_log_size * 2);
+        assert_eq!(
+            game.bytes_out,
+            std::fs::metadata(output.join("round-1/game.log"))
+                .unwrap()
+                .len()
+                * 2
+        );
+
+        let passthrough = summary.strategy_stats["passthrough_sanitized"];
+        assert_eq!(passthrough.files, 2);
+        assert_eq!(passthrough.bytes_in, "from 1.2.3.4\n".len() as u64 * 2);
+        assert_eq!(passthrough.bytes_out, "from -censored-\n".len() as u64 * 2);
+
+        let stats = StrategyStats {
+            bytes_in: 5_000_000,
+            elapsed: Duration::from_secs(2),
+            ..Default::default()
+        };
+        assert_eq!(stats.megabytes_per_second(), Some(2.5));
+        assert_eq!(StrategyStats::default().megabytes_per_second(), None);
+
+        for directory in [input, output] {
+            std::fs::remove_dir_all(directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_sanitize_tree_to_sink() {
+        #[derive(Default)]
+        struct MemorySink(HashMap<PathBuf, Vec<u8>>);
+
+        impl SanitizationSink for MemorySink {
+            fn write_sanitized(
+                &mut self,
+                relative_path: &Path,
+                contents: &[u8],
+            ) -> eyre::Result<()> {
+                self.0.insert(relative_path.to_owned(), contents.to_owned());
+                Ok(())
+            }
+        }
+
+        let input = test_directory("sink-input");
+
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(round.join("game.log"), "[12:00:00] GAME: from 1.2.3.4\n").unwrap();
+        std::fs::write(round.join("secret.log"), "not published").unwrap();
+
+        let mut sink = MemorySink::default();
+        let summary = sanitize_tree_to_sink(&input, &test_options(), &mut sink).unwrap();
+
+        assert_eq!(summary.files_written, 1);
+        assert_eq!(
+            sink.0,
+            HashMap::from([(
+                PathBuf::from("round-1/game.log"),
+                b"[12:00:00] GAME: from -censored-\n".to_vec()
+            )])
+        );
+
+        std::fs::remove_dir_all(&input).unwrap();
+    }
+
+    #[test]
+    fn test_profiles() {
+        for profile in [Profile::TgstationPublic, Profile::StrictMinimal] {
+            assert_eq!(Profile::from_name(profile.name()), Some(profile));
+        }
+
+        let input = test_directory("profiles-input");
+        let round = input.join("round-1");
+        std::fs::create_dir_all(&round).unwrap();
+        std::fs::write(
+            round.join("game.log"),
+            "[2024-01-02 03:04:05.678] GAME: hello from 1.2.3.4\n",
+        )
+        .unwrap();
+        std::fs::write(
+            round.join("paper.log"),
+            "[2024-01-02 03:04:05.678] PAPER: Somebody wrote on \"paper\": \"secret\"\n",
+        )
+        .unwrap();
+        std::fs::write(round.join("perf-1.csv"), "1,2,3\n").unwrap();
+
+        // The official profile is what the server does
+        let output = test_directory("profiles-public-output");
+        let summary = sanitize_tree(
+            &input,
+            &output,
+            &SanitizeTreeOptions::from_profile(Profile::TgstationPublic),
+        )
+        .unwrap();
+        assert_eq!(summary.files_written, 3);
+        for filename in ["game.log", "paper.log", "perf-1.csv"] {
+            let path = round.join(filename);
+            assert_eq!(
+                std::fs::read_to_string(output.join("round-1").join(filename)).unwrap(),
+                crate::parsers::get_file_sanitization_strategy(&path).unwrap()(
+                    std::fs::read_to_string(&path).unwrap()
+                ),
+                "{filename}"
+            );
+        }
+        std::fs::remove_dir_all(&output).unwrap();
+
+        let output = test_directory("profiles-strict-output");
+        let summary = sanitize_tree(
+            &input,
+            &output,
+            &SanitizeTreeOptions::from_profile(Profile::StrictMinimal),
+        )
+        .unwrap();
+        assert_eq!(summary.files_written, 2);
+        assert_eq!(summary.files_skipped, 1);
+        assert!(std::fs::read_to_string(output.join("round-1/paper.log"))
+            .unwrap()
+            .ends_with("wrote on -censored(paper content)-\n"));
+        std::fs::remove_dir_all(&output).unwrap();
+
+        std::fs::remove_dir_all(&input).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+// Everything needed to sanitize logs in one place, for anyone using this as a library
+// rather than wiring up the policies and ongoing round protection themselves.
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use eyre::Context;
+use regex::Regex;
+
+use crate::{
+    ongoing_round_protection::OngoingRoundProtection,
+    parsers::{
+        game::GameLogPolicy, read_to_string_async, url_filtering::UrlFiltering, SanitizationPolicy,
+    },
+    sanitize_tree::{
+        sanitize_tree, Profile, RoundFileDecision, RoundFileGate, SanitizeTreeOptions, Summary,
+    },
+};
+
+/// Configures a [`Sanitizer`]. IPs are always filtered, everything else starts out as the defaults.
+///
+/// ```
+/// use std::path::Path;
+/// use tg_public_log_parser::sanitizer::SanitizerBuilder;
+///
+/// let sanitizer = SanitizerBuilder::new()
+///     .keyword_blocklist(["syndicate"])
+///     .build();
+///
+/// let sanitized = sanitizer.sanitize_contents(
+///     Path::new("round-1/cargo.html"),
+///     "<b>Ordered by the Syndicate from 1.2.3.4</b>".to_owned(),
+/// );
+///
+/// assert_eq!(
+///     sanitized.as_deref(),
+///     Some("<b>Ordered by the -censored(keyword)- from -censored-</b>")
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct SanitizerBuilder {
+    tree_options: SanitizeTreeOptions,
+    ongoing_round_protection: Option<Arc<OngoingRoundProtection>>,
+}
+
+impl SanitizerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts from a profile's settings rather than the defaults, which the other methods can then change
+    pub fn from_profile(profile: Profile) -> Self {
+        Self {
+            tree_options: SanitizeTreeOptions::from_profile(profile),
+            ongoing_round_protection: None,
+        }
+    }
+
+    // See StrictMode for what this changes. Settings made after this can loosen it again.
+    pub fn strict_mode(mut self) -> Self {
+        self.tree_options.policy.apply_strict_mode();
+        self
+    }
+
+    pub fn game_policy(mut self, game_policy: GameLogPolicy) -> Self {
+        self.tree_options.policy.game = game_policy;
+        self
+    }
+
+    pub fn url_filtering(mut self, url_filtering: UrlFiltering) -> Self {
+        self.tree_options.policy.game.url_filtering = url_filtering;
+        self
+    }
+
+    // See SanitizationPolicy::label_ip_censors
+    pub fn label_ip_censors(mut self) -> Self {
+        self.tree_options.policy.label_ip_censors();
+        self
+    }
+
+    // Publish files we don't know about by their extension, see SanitizationPolicy::extension_fallback
+    pub fn extension_fallback(mut self) -> Self {
+        self.tree_options.policy.extension_fallback = true;
+        self
+    }
+
+    // Censor what players wrote in paper logs, keeping who wrote it and where
+    pub fn censor_paper_content(mut self) -> Self {
+        self.tree_options.policy.paper.censor_content = true;
+        self
+    }
+
+    // Censor what was said in radio and PDA messages, keeping who sent them and on what frequency
+    pub fn censor_comms_messages(mut self) -> Self {
+        self.tree_options.policy.comms.censor_messages = true;
+        self
+    }
+
+    pub fn keyword_blocklist(
+        mut self,
+        keywords: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.tree_options.policy.passthrough.keyword_blocklist =
+            keywords.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // Replaces the keys censored in round_end_data, such as to add "ckey"
+    pub fn round_end_data_sensitive_keys(
+        mut self,
+        keys: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.tree_options.policy.round_end_data.sensitive_keys =
+            keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn mask_coordinates_in(
+        mut self,
+        filenames: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.tree_options.policy.passthrough.mask_coordinates_in =
+            filenames.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn censor_base64_blobs(mut self, min_length: usize) -> Self {
+        self.tree_options.policy.passthrough.base64_min_length = Some(min_length);
+        self
+    }
+
+    pub fn ongoing_round_protection(
+        mut self,
+        ongoing_round_protection: Arc<OngoingRoundProtection>,
+    ) -> Self {
+        self.ongoing_round_protection = Some(ongoing_round_protection);
+        self
+    }
+
+    // Only sanitize files with names matching this when sanitizing a tree
+    pub fn only_filenames(mut self, only_filenames: Regex) -> Self {
+        self.tree_options.only_filenames = Some(only_filenames);
+        self
+    }
+
+    // Write gzipped files when sanitizing a tree, at a level from 0 to 9
+    pub fn gzip_output(mut self, level: u32) -> Self {
+        self.tree_options.gzip_level = Some(level);
+        self
+    }
+
+    // Copy files without a strategy here when sanitizing a tree, see SanitizeTreeOptions::quarantine_root
+    pub fn quarantine_root(mut self, quarantine_root: impl Into<PathBuf>) -> Self {
+        self.tree_options.quarantine_root = Some(quarantine_root.into());
+        self
+    }
+
+    // Leave out files bigger than this many bytes, see SanitizeTreeOptions::max_file_size
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.tree_options.max_file_size = Some(max_file_size);
+        self
+    }
+
+    // Leave out rounds at or below this id when sanitizing a tree, see SanitizeTreeOptions::since_round
+    pub fn since_round(mut self, since_round: u64) -> Self {
+        self.tree_options.since_round = Some(since_round);
+        self
+    }
+
+    // Decide per round whether to publish, defer, or withhold each file when sanitizing a tree,
+    // see SanitizeTreeOptions::round_file_gate
+    pub fn round_file_gate(
+        mut self,
+        gate: impl Fn(&Path, &str) -> RoundFileDecision + Send + Sync + 'static,
+    ) -> Self {
+        self.tree_options.round_file_gate = Some(RoundFileGate::new(gate));
+        self
+    }
+
+    pub fn read_retries(mut self, read_retries: u32, retry_delay: Duration) -> Self {
+        self.tree_options.read_retries = read_retries;
+        self.tree_options.retry_delay = retry_delay;
+        self
+    }
+
+    pub fn build(self) -> Sanitizer {
+        Sanitizer {
+            tree_options: self.tree_options,
+            ongoing_round_protection: self.ongoing_round_protection,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Sanitizer {
+    tree_options: SanitizeTreeOptions,
+    ongoing_round_protection: Option<Arc<OngoingRoundProtection>>,
+}
+
+impl Sanitizer {
+    pub fn policy(&self) -> &SanitizationPolicy {
+        &self.tree_options.policy
+    }
+
+    // None if the file would never be published. Doesn't check for ongoing rounds.
+    pub fn sanitize_contents(&self, path: &Path, contents: String) -> Option<String> {
+        let strategy = self.policy().sanitization_strategy(path)?;
+        Some(strategy.sanitize(path, contents, self.policy()))
+    }
+
+    // None if the file would never be published, or is from an ongoing round
+    pub async fn sanitize_file(&self, path: &Path) -> eyre::Result<Option<String>> {
+        if let Some(ongoing_round_protection) = &self.ongoing_round_protection {
+            if ongoing_round_protection.path_is_ongoing_round(path).await? {
+                return Ok(None);
+            }
+        }
+
+        let Some(strategy) = self.policy().sanitization_strategy(path) else {
+            return Ok(None);
+        };
+
+        let contents = read_to_string_async(path, self.tree_options.max_file_size)
+            .await
+            .with_context(|| format!("reading {}", path.display()))?;
+
+        Ok(Some(strategy.sanitize(path, contents, self.policy())))
+    }
+
+    // Like sanitize_file, but writes it to output_path with tokio::fs, creating any folders it needs.
+    // false if nothing was written.
+    pub async fn sanitize_file_to(&self, path: &Path, output_path: &Path) -> eyre::Result<bool> {
+        let Some(sanitized) = self.sanitize_file(path).await? else {
+            return Ok(false);
+        };
+
+        if let Some(parent) = output_path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+
+        tokio::fs::write(output_path, sanitized)
+            .await
+            .with_context(|| format!("writing {}", output_path.display()))?;
+
+        Ok(true)
+    }
+
+    // Round ids are only fetched once at the start, so a round that ends partway through is left out until the next run
+    pub async fn sanitize_tree(
+        &self,
+        input_root: &Path,
+        output_root: &Path,
+    ) -> eyre::Result<Summary> {
+        let mut tree_options = self.tree_options.clone();
+
+        if let Some(ongoing_round_protection) = &self.ongoing_round_protection {
+            tree_options.ongoing_rounds = Some(ongoing_round_protection.snapshot().await?);
+        }
+
+        let input_root = PathBuf::from(input_root);
+        let output_root = PathBuf::from(output_root);
+
+        tokio::task::spawn_blocking(move || sanitize_tree(&input_root, &output_root, &tree_options))
+            .await
+            .context("sanitize_tree panicked")?
+    }
+}
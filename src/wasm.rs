@@ -0,0 +1,26 @@
+// For sanitizing in the browser, so logs can be previewed without uploading the raw ones.
+// Build with --no-default-features --features wasm for wasm32-unknown-unknown.
+use wasm_bindgen::prelude::*;
+
+use crate::parsers::{game, ip_filtering, runtimes};
+
+#[wasm_bindgen(js_name = processGameLog)]
+pub fn process_game_log(contents: String) -> String {
+    game::process_game_log(contents)
+}
+
+#[wasm_bindgen(js_name = processRuntimesLog)]
+pub fn process_runtimes_log(contents: String) -> String {
+    runtimes::process_runtimes_log(contents)
+}
+
+#[wasm_bindgen(js_name = filterIps)]
+pub fn filter_ips(contents: &str) -> String {
+    ip_filtering::filter_ips(contents).into_owned()
+}
+
+// undefined if the file would never be published
+#[wasm_bindgen(js_name = sanitizeContents)]
+pub fn sanitize_contents(filename: &str, contents: String) -> Option<String> {
+    crate::parsers::sanitize_contents(filename, contents)
+}